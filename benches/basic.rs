@@ -1,6 +1,6 @@
 use std::hint::black_box;
 
-use compose_rt::{Composer, Root};
+use compose_rt::{Composer, MaybeSendSync, Root};
 use criterion::{criterion_group, criterion_main, Criterion};
 
 type Scope<S> = compose_rt::Scope<S, ()>;
@@ -13,15 +13,15 @@ pub struct Text;
 pub trait Html {
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static;
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static;
 
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 }
 
 impl<S> Html for Scope<S>
@@ -31,7 +31,7 @@ where
     #[track_caller]
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static,
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Div>();
         self.create_node(child_scope, content, || {}, |_, _| {}, |_, _, _| {});
@@ -40,7 +40,7 @@ where
     #[track_caller]
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Button>();
         self.create_node(
@@ -55,7 +55,7 @@ where
     #[track_caller]
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Text>();
         self.create_node(
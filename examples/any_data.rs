@@ -2,9 +2,9 @@ use std::any::Any;
 use std::env;
 use std::fmt::Debug;
 
-use compose_rt::{AnyData, ComposeNode, Composer, Root};
+use compose_rt::{AnyData, ComposeNode, Composer, MaybeSendSync, Root};
 
-pub trait Data: Debug + 'static {
+pub trait Data: Debug + MaybeSendSync + 'static {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
@@ -75,15 +75,15 @@ impl Data for Text {
 pub trait Html {
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static;
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static;
 
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 }
 
 impl<S> Html for Scope<S>
@@ -93,7 +93,7 @@ where
     #[track_caller]
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static,
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Div>();
         self.create_any_node(child_scope, content, || {}, |_, _| Div, |_, _, _| {});
@@ -102,7 +102,7 @@ where
     #[track_caller]
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Button>();
         self.create_any_node(
@@ -117,7 +117,7 @@ where
     #[track_caller]
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Text>();
         self.create_any_node(
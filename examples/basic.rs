@@ -1,6 +1,6 @@
 use std::env;
 
-use compose_rt::{ComposeNode, Composer, Root};
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, Root};
 
 #[derive(Debug)]
 pub struct Data(String);
@@ -30,15 +30,15 @@ pub struct Text;
 pub trait Html {
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static;
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static;
 
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static;
+        T: Into<String> + Clone + MaybeSendSync + 'static;
 }
 
 impl<S> Html for Scope<S>
@@ -48,7 +48,7 @@ where
     #[track_caller]
     fn div<C>(&self, content: C)
     where
-        C: Fn(Scope<Div>) + Clone + 'static,
+        C: Fn(Scope<Div>) + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Div>();
         self.create_node(
@@ -63,7 +63,7 @@ where
     #[track_caller]
     fn button<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Button>();
         self.create_node(
@@ -80,7 +80,7 @@ where
     #[track_caller]
     fn text<T>(&self, text: T)
     where
-        T: Into<String> + Clone + 'static,
+        T: Into<String> + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Text>();
         self.create_node(
@@ -0,0 +1,108 @@
+use compose_rt::layout::{Constraints, Layout, MeasureScope, Size};
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, NodeKey, Recomposer, Root, Scope};
+
+#[derive(Debug)]
+struct LayoutNode {
+    widget: String,
+}
+
+impl ComposeNode for LayoutNode {
+    type Context = ();
+}
+
+type UiScope<S> = Scope<S, LayoutNode>;
+
+struct ColumnNode;
+struct ColumnItem;
+struct TextNode;
+
+// every child gets the width of the widest one
+struct MinWidthColumn<C> {
+    content: C,
+}
+
+impl<C> Layout<LayoutNode> for MinWidthColumn<C>
+where
+    C: Fn(UiScope<ColumnItem>) + Clone + MaybeSendSync + 'static,
+{
+    fn measure(&self, scope: &mut MeasureScope<LayoutNode>, constraints: Constraints) -> Size {
+        let children = scope.subcompose::<ColumnItem, _>("content", self.content.clone());
+        let width = children
+            .iter()
+            .map(|child| child.measure(constraints.loosen()).width())
+            .fold(0.0, f32::max);
+        let fill = Constraints::new(width, width, 0.0, constraints.max_height);
+        let mut height = 0.0;
+        for child in children {
+            let placeable = child.measure(fill);
+            scope.place(placeable, 0.0, height);
+            height += placeable.height();
+        }
+        Size::new(width, height)
+    }
+}
+
+fn column<S, C>(scope: UiScope<S>, content: C)
+where
+    S: 'static,
+    C: Fn(UiScope<ColumnItem>) + Clone + MaybeSendSync + 'static,
+{
+    scope.layout(
+        scope.child::<ColumnNode>(),
+        MinWidthColumn { content },
+        || (),
+        |_, _| LayoutNode {
+            widget: "Column".to_string(),
+        },
+        |_, _, _| {},
+    );
+}
+
+fn text<S: 'static>(scope: UiScope<S>, value: &str) {
+    let width = value.chars().count() as f32;
+    let value = value.to_string();
+    scope.layout(
+        scope.child::<TextNode>(),
+        move |_: &mut MeasureScope<LayoutNode>, _: Constraints| Size::new(width, 1.0),
+        move || value.clone(),
+        |value, _| LayoutNode {
+            widget: format!("Text(\"{}\")", value),
+        },
+        |node, value, _| node.widget = format!("Text(\"{}\")", value),
+    );
+}
+
+fn app(scope: UiScope<Root>) {
+    column(scope, |scope| {
+        text(scope, "Title");
+        text(
+            scope,
+            "This is a much longer line that drives the minimum width",
+        );
+        text(scope, "Footer");
+    });
+}
+
+fn print_layout(recomposer: &Recomposer<(), LayoutNode>, node_key: NodeKey, depth: usize) {
+    recomposer.with_composer(|c| {
+        let node = &c.nodes[node_key];
+        if let (Some(data), Some(placement)) = (&node.data, recomposer.placement(node_key)) {
+            println!(
+                "{}{} at {:?} size {:?}",
+                "  ".repeat(depth),
+                data.widget,
+                placement.offset,
+                placement.size
+            );
+        }
+        for child in &node.children {
+            print_layout(recomposer, *child, depth + 1);
+        }
+    });
+}
+
+fn main() {
+    let mut recomposer = Composer::compose(app, ());
+    recomposer.layout(Constraints::loose(80.0, 24.0));
+    print_layout(&recomposer, recomposer.root_node_key(), 0);
+}
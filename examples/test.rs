@@ -1,6 +1,6 @@
 use std::fmt::Debug;
 
-use compose_rt::{ComposeNode, Composer, Loc, Root};
+use compose_rt::{ComposeNode, Composer, Loc, MaybeSendSync, Root};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Data(Loc);
@@ -37,7 +37,7 @@ pub struct Left;
 pub trait ComposerTest {
     fn container<C>(&self, content: C)
     where
-        C: Fn(Scope<Container>) + Clone + 'static;
+        C: Fn(Scope<Container>) + Clone + MaybeSendSync + 'static;
 
     fn leaf(&self);
 }
@@ -49,7 +49,7 @@ where
     #[track_caller]
     fn container<C>(&self, content: C)
     where
-        C: Fn(Scope<Container>) + Clone + 'static,
+        C: Fn(Scope<Container>) + Clone + MaybeSendSync + 'static,
     {
         let child_scope = self.child::<Container>();
         let data = Data::new();
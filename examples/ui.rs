@@ -1,7 +1,6 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
-use compose_rt::{ComposeNode, Composer, Root, Scope, SlotId, SubcomposeScope};
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, Root, Scope, SubcomposeScope};
 
 #[derive(Debug)]
 struct LayoutNode {
@@ -34,7 +33,7 @@ struct ColumnSlot;
 
 #[derive(Clone)]
 enum LayoutContext {
-    Measure { widths: Rc<RefCell<Vec<usize>>> },
+    Measure { widths: Arc<Mutex<Vec<usize>>> },
     Render,
 }
 
@@ -56,7 +55,7 @@ impl<S: 'static> Ui<S> {
 
 fn render_text<S: 'static>(scope: UiScope<S>, value: String, modifier: Modifier) {
     let text_scope = scope.child::<TextNode>();
-    let display = Rc::new(value);
+    let display = Arc::new(value);
     let display_for_input = display.clone();
     scope.create_node(
         text_scope,
@@ -77,7 +76,7 @@ fn render_text<S: 'static>(scope: UiScope<S>, value: String, modifier: Modifier)
 
 fn render_button<S: 'static>(scope: UiScope<S>, value: String, modifier: Modifier) {
     let button_scope = scope.child::<ButtonNode>();
-    let display = Rc::new(value);
+    let display = Arc::new(value);
     let display_for_input = display.clone();
     scope.create_node(
         button_scope,
@@ -99,7 +98,7 @@ fn render_button<S: 'static>(scope: UiScope<S>, value: String, modifier: Modifie
 impl<S: 'static> UiDsl for Ui<S> {
     fn text(&self, modifier: Modifier, text: String) {
         if let LayoutContext::Measure { widths } = self.scope.context() {
-            widths.borrow_mut().push(text_width(&text));
+            widths.lock().unwrap().push(text_width(&text));
         } else {
             render_text(self.scope.scope(), text, modifier);
         }
@@ -107,7 +106,7 @@ impl<S: 'static> UiDsl for Ui<S> {
 
     fn button(&self, modifier: Modifier, label: String) {
         if let LayoutContext::Measure { widths } = self.scope.context() {
-            widths.borrow_mut().push(text_width(&label));
+            widths.lock().unwrap().push(text_width(&label));
         } else {
             render_button(self.scope.scope(), label, modifier);
         }
@@ -117,39 +116,35 @@ impl<S: 'static> UiDsl for Ui<S> {
 fn resize_min_width<S, C>(scope: UiScope<S>, modifier: Modifier, content: C)
 where
     S: 'static,
-    C: Fn(Modifier, &mut dyn UiDsl) + Clone + 'static,
+    C: Fn(Modifier, &mut dyn UiDsl) + Clone + MaybeSendSync + 'static,
 {
     let content = content.clone();
-    let metrics = Rc::new(RefCell::new(Vec::new()));
+    let metrics = Arc::new(Mutex::new(Vec::new()));
     let measure_content = content.clone();
     let render_content = content.clone();
     scope.subcompose(move |mut registry| {
         let mut m = modifier;
-        metrics.borrow_mut().clear();
+        metrics.lock().unwrap().clear();
         let metrics_for_measure = metrics.clone();
         let measure_content = measure_content.clone();
         registry.subcompose::<ColumnSlot, _, _>(
-            SlotId::from("measure"),
+            "measure",
             LayoutContext::Measure {
                 widths: metrics_for_measure.clone(),
             },
             move |slot| {
                 let mut runner = Ui::new(slot);
                 let callback = measure_content.clone();
-                callback(m.clone(), &mut runner as &mut dyn UiDsl);
+                callback(m, &mut runner as &mut dyn UiDsl);
             },
         );
-        m.min_width = metrics.borrow().iter().copied().max().unwrap_or(0);
+        m.min_width = metrics.lock().unwrap().iter().copied().max().unwrap_or(0);
         let render_content = render_content.clone();
-        registry.subcompose::<ColumnSlot, _, _>(
-            SlotId::from("render"),
-            LayoutContext::Render,
-            move |slot| {
-                let mut runner = Ui::new(slot);
-                let callback = render_content.clone();
-                callback(m.clone(), &mut runner as &mut dyn UiDsl);
-            },
-        );
+        registry.subcompose::<ColumnSlot, _, _>("render", LayoutContext::Render, move |slot| {
+            let mut runner = Ui::new(slot);
+            let callback = render_content.clone();
+            callback(m, &mut runner as &mut dyn UiDsl);
+        });
     });
 }
 
@@ -160,7 +155,7 @@ fn text_width(text: &str) -> usize {
 fn column<S, C>(scope: UiScope<S>, modifier: Modifier, width: usize, content: C)
 where
     S: 'static,
-    C: Fn(UiScope<ColumnNode>) + Clone + 'static,
+    C: Fn(UiScope<ColumnNode>) + Clone + MaybeSendSync + 'static,
 {
     let column_scope = scope.child::<ColumnNode>();
     scope.create_node(
@@ -168,7 +163,7 @@ where
         content,
         move || (width, modifier),
         move |(width, modifier), _| LayoutNode {
-            widget: format!("Column"),
+            widget: "Column".to_string(),
             width,
             min_width: modifier.min_width,
         },
@@ -0,0 +1,281 @@
+use std::any::Any;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::storage::{AnyBox, MaybeSendSync};
+
+pub trait Interpolate: Clone + PartialEq + MaybeSendSync + 'static {
+    /// `fraction` may leave `0..=1` when a spring overshoots.
+    fn interpolate(&self, to: &Self, fraction: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    #[inline(always)]
+    fn interpolate(&self, to: &Self, fraction: f32) -> Self {
+        self + (to - self) * fraction
+    }
+}
+
+impl Interpolate for f64 {
+    #[inline(always)]
+    fn interpolate(&self, to: &Self, fraction: f32) -> Self {
+        self + (to - self) * fraction as f64
+    }
+}
+
+macro_rules! impl_interpolate_tuple {
+    ($($name:ident $idx:tt),+) => {
+        impl<$($name),+> Interpolate for ($($name,)+)
+        where
+            $($name: Interpolate,)+
+        {
+            #[inline(always)]
+            fn interpolate(&self, to: &Self, fraction: f32) -> Self {
+                ($(self.$idx.interpolate(&to.$idx, fraction),)+)
+            }
+        }
+    };
+}
+
+impl_interpolate_tuple!(A 0, B 1);
+impl_interpolate_tuple!(A 0, B 1, C 2);
+impl_interpolate_tuple!(A 0, B 1, C 2, D 3);
+
+#[derive(Clone, Copy)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Custom(fn(f32) -> f32),
+}
+
+impl Easing {
+    pub fn transform(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Custom(func) => func(t),
+        }
+    }
+}
+
+impl Debug for Easing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Easing::Linear => write!(f, "Linear"),
+            Easing::EaseIn => write!(f, "EaseIn"),
+            Easing::EaseOut => write!(f, "EaseOut"),
+            Easing::EaseInOut => write!(f, "EaseInOut"),
+            Easing::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AnimationSpec<T> {
+    Tween {
+        duration_nanos: u64,
+        easing: Easing,
+    },
+    /// A unit mass spring, `damping_ratio` below 1 overshoots the target.
+    Spring {
+        stiffness: f32,
+        damping_ratio: f32,
+    },
+    /// Values at the given offsets, linearly interpolated in between.
+    Keyframes {
+        duration_nanos: u64,
+        frames: Vec<(u64, T)>,
+    },
+}
+
+impl<T> AnimationSpec<T> {
+    #[inline(always)]
+    pub fn tween(duration_nanos: u64) -> Self {
+        AnimationSpec::Tween {
+            duration_nanos,
+            easing: Easing::EaseInOut,
+        }
+    }
+
+    #[inline(always)]
+    pub fn tween_with(duration_nanos: u64, easing: Easing) -> Self {
+        AnimationSpec::Tween {
+            duration_nanos,
+            easing,
+        }
+    }
+
+    /// `stiffness` and `damping_ratio` are raised to small positive minimums, an undamped
+    /// or slack spring would never settle.
+    #[inline(always)]
+    pub fn spring(stiffness: f32, damping_ratio: f32) -> Self {
+        AnimationSpec::Spring {
+            stiffness: clamp_or(stiffness, SPRING_MIN_STIFFNESS),
+            damping_ratio: clamp_or(damping_ratio, SPRING_MIN_DAMPING_RATIO),
+        }
+    }
+
+    #[inline(always)]
+    pub fn keyframes(duration_nanos: u64, frames: Vec<(u64, T)>) -> Self {
+        AnimationSpec::Keyframes {
+            duration_nanos,
+            frames,
+        }
+    }
+}
+
+impl<T> Default for AnimationSpec<T> {
+    fn default() -> Self {
+        AnimationSpec::spring(1500.0, 1.0)
+    }
+}
+
+const SPRING_MIN_STIFFNESS: f32 = 1.0;
+const SPRING_MIN_DAMPING_RATIO: f32 = 0.05;
+// the spring is settled once it is within 1e-3 of the target, and its velocity scaled by the
+// natural frequency is as small
+const SPRING_SETTLE: f32 = 1e-3;
+
+// NaN is replaced by the minimum as well
+#[inline(always)]
+fn clamp_or(value: f32, min: f32) -> f32 {
+    if value >= min {
+        value
+    } else {
+        min
+    }
+}
+
+pub(crate) struct Animation<T> {
+    pub from: T,
+    pub to: T,
+    pub spec: AnimationSpec<T>,
+    pub start_nanos: u64,
+    pub running: bool,
+}
+
+impl<T> Animation<T>
+where
+    T: Interpolate,
+{
+    pub fn idle(value: T, spec: AnimationSpec<T>) -> Self {
+        Self {
+            from: value.clone(),
+            to: value,
+            spec,
+            start_nanos: 0,
+            running: false,
+        }
+    }
+
+    pub fn restart(&mut self, from: T, to: T, spec: AnimationSpec<T>, start_nanos: u64) {
+        self.from = from;
+        self.to = to;
+        self.spec = spec;
+        self.start_nanos = start_nanos;
+        self.running = true;
+    }
+
+    fn value_at(&self, frame_nanos: u64) -> (T, bool) {
+        let elapsed = frame_nanos.saturating_sub(self.start_nanos);
+        match &self.spec {
+            AnimationSpec::Tween {
+                duration_nanos,
+                easing,
+            } => {
+                if elapsed >= *duration_nanos {
+                    return (self.to.clone(), true);
+                }
+                let t = elapsed as f32 / *duration_nanos as f32;
+                (self.from.interpolate(&self.to, easing.transform(t)), false)
+            }
+            AnimationSpec::Spring {
+                stiffness,
+                damping_ratio,
+            } => {
+                let t = elapsed as f32 / 1e9;
+                let (fraction, velocity) = spring_fraction(*stiffness, *damping_ratio, t);
+                if (1.0 - fraction).abs() < SPRING_SETTLE && velocity.abs() < SPRING_SETTLE {
+                    return (self.to.clone(), true);
+                }
+                (self.from.interpolate(&self.to, fraction), false)
+            }
+            AnimationSpec::Keyframes {
+                duration_nanos,
+                frames,
+            } => {
+                if elapsed >= *duration_nanos {
+                    return (self.to.clone(), true);
+                }
+                let (mut prev_at, mut prev) = (0, &self.from);
+                for (at, value) in frames {
+                    if elapsed < *at {
+                        break;
+                    }
+                    (prev_at, prev) = (*at, value);
+                }
+                let (next_at, next) = frames
+                    .iter()
+                    .find(|(at, _)| elapsed < *at)
+                    .map_or((*duration_nanos, &self.to), |(at, value)| (*at, value));
+                let t = (elapsed - prev_at) as f32 / (next_at - prev_at) as f32;
+                (prev.interpolate(next, t), false)
+            }
+        }
+    }
+}
+
+// progress of a unit spring released at 0 towards 1, and its velocity scaled by the natural
+// frequency, fields set directly on the spec are clamped like `AnimationSpec::spring` does
+fn spring_fraction(stiffness: f32, damping_ratio: f32, t: f32) -> (f32, f32) {
+    let omega = clamp_or(stiffness, SPRING_MIN_STIFFNESS).sqrt();
+    let zeta = clamp_or(damping_ratio, SPRING_MIN_DAMPING_RATIO);
+    if zeta < 1.0 {
+        let decay = zeta * omega;
+        let damped = omega * (1.0 - zeta * zeta).sqrt();
+        let envelope = (-decay * t).exp();
+        let x = 1.0 - envelope * ((damped * t).cos() + decay / damped * (damped * t).sin());
+        let v = envelope * omega / damped * (damped * t).sin();
+        (x, v)
+    } else if zeta == 1.0 {
+        let envelope = (-omega * t).exp();
+        let x = 1.0 - envelope * (1.0 + omega * t);
+        let v = envelope * omega * t;
+        (x, v)
+    } else {
+        let root = (zeta * zeta - 1.0).sqrt();
+        let r1 = -omega * (zeta - root);
+        let r2 = -omega * (zeta + root);
+        let x = 1.0 - (r2 * (r1 * t).exp() - r1 * (r2 * t).exp()) / (r2 - r1);
+        let v = -r1 * r2 / omega * ((r1 * t).exp() - (r2 * t).exp()) / (r2 - r1);
+        (x, v)
+    }
+}
+
+pub(crate) trait AnyAnimation: MaybeSendSync {
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn is_running(&self) -> bool;
+    fn step(&mut self, frame_nanos: u64) -> AnyBox;
+}
+
+impl<T> AnyAnimation for Animation<T>
+where
+    T: Interpolate,
+{
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn step(&mut self, frame_nanos: u64) -> AnyBox {
+        let (value, finished) = self.value_at(frame_nanos);
+        self.running = !finished;
+        Box::new(value)
+    }
+}
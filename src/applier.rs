@@ -0,0 +1,120 @@
+use slab::Slab;
+
+use crate::composer::Node;
+use crate::{ComposeNode, MaybeSendSync, NodeKey};
+
+/// Receives the structural changes of the node tree so a backend can mirror
+/// them incrementally instead of rescanning `Composer::nodes`.
+///
+/// Nodes are inserted before their data is created, the data then arrives
+/// through `update`, which is also called every time the node is updated.
+/// Removing a node implies its whole subtree is gone.
+///
+/// The changes of a pass are held back until it completes and then sent
+/// between `on_begin` and `on_end`, so a pass paused by
+/// `Recomposer::recompose_with_budget` leaves the backend untouched.
+/// Changes made outside a pass are sent with the next one.
+pub trait Applier<N>: MaybeSendSync
+where
+    N: ComposeNode,
+{
+    fn on_begin(&mut self) {}
+
+    fn on_end(&mut self) {}
+
+    fn insert_at(&mut self, parent: NodeKey, index: usize, node_key: NodeKey);
+
+    fn remove(&mut self, parent: NodeKey, index: usize, node_key: NodeKey);
+
+    fn move_node(&mut self, parent: NodeKey, from: usize, to: usize, node_key: NodeKey);
+
+    fn update(&mut self, node_key: NodeKey, node: &N);
+}
+
+enum ApplierOp {
+    InsertAt(NodeKey, usize, NodeKey),
+    Remove(NodeKey, usize, NodeKey),
+    Move(NodeKey, usize, usize, NodeKey),
+    Update(NodeKey),
+}
+
+// buffers the changes for the applier until the pass completes
+pub(crate) struct ApplierQueue<N>
+where
+    N: ComposeNode,
+{
+    applier: Option<Box<dyn Applier<N>>>,
+    ops: Vec<ApplierOp>,
+}
+
+impl<N> ApplierQueue<N>
+where
+    N: ComposeNode,
+{
+    pub fn new() -> Self {
+        Self {
+            applier: None,
+            ops: Vec::new(),
+        }
+    }
+
+    // the new applier starts from a replay of the current tree, pending changes included
+    pub fn set(&mut self, applier: Box<dyn Applier<N>>) {
+        self.applier = Some(applier);
+        self.ops.clear();
+    }
+
+    #[inline(always)]
+    fn push(&mut self, op: ApplierOp) {
+        if self.applier.is_some() {
+            self.ops.push(op);
+        }
+    }
+
+    #[inline(always)]
+    pub fn insert_at(&mut self, parent: NodeKey, index: usize, node_key: NodeKey) {
+        self.push(ApplierOp::InsertAt(parent, index, node_key));
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, parent: NodeKey, index: usize, node_key: NodeKey) {
+        self.push(ApplierOp::Remove(parent, index, node_key));
+    }
+
+    #[inline(always)]
+    pub fn move_node(&mut self, parent: NodeKey, from: usize, to: usize, node_key: NodeKey) {
+        self.push(ApplierOp::Move(parent, from, to, node_key));
+    }
+
+    #[inline(always)]
+    pub fn update(&mut self, node_key: NodeKey) {
+        self.push(ApplierOp::Update(node_key));
+    }
+
+    // updates send the data the node has now, nodes disposed since are skipped
+    pub fn flush(&mut self, nodes: &Slab<Node<N>>) {
+        let Some(applier) = self.applier.as_mut() else {
+            return;
+        };
+        applier.on_begin();
+        for op in self.ops.drain(..) {
+            match op {
+                ApplierOp::InsertAt(parent, index, node_key) => {
+                    applier.insert_at(parent, index, node_key)
+                }
+                ApplierOp::Remove(parent, index, node_key) => {
+                    applier.remove(parent, index, node_key)
+                }
+                ApplierOp::Move(parent, from, to, node_key) => {
+                    applier.move_node(parent, from, to, node_key)
+                }
+                ApplierOp::Update(node_key) => {
+                    if let Some(data) = nodes.get(node_key).and_then(|node| node.data.as_ref()) {
+                        applier.update(node_key, data);
+                    }
+                }
+            }
+        }
+        applier.on_end();
+    }
+}
@@ -0,0 +1,65 @@
+use std::any::Any;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::{ComposeNode, State};
+
+pub struct ErrorBoundary;
+
+pub struct BoundaryError<N>
+where
+    N: ComposeNode,
+{
+    message: String,
+    attempt: State<usize, N>,
+}
+
+impl<N> BoundaryError<N>
+where
+    N: ComposeNode,
+{
+    #[inline(always)]
+    pub(crate) fn new(payload: &(dyn Any + Send), attempt: State<usize, N>) -> Self {
+        let message = if let Some(msg) = payload.downcast_ref::<&str>() {
+            msg.to_string()
+        } else if let Some(msg) = payload.downcast_ref::<String>() {
+            msg.clone()
+        } else {
+            "composable panicked".to_string()
+        };
+        Self { message, attempt }
+    }
+
+    #[inline(always)]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Composes the boundary content again on the next recompose.
+    #[inline(always)]
+    pub fn retry(&self) {
+        self.attempt.with_mut_untracked(|attempt| *attempt += 1);
+    }
+}
+
+impl<N> Clone for BoundaryError<N>
+where
+    N: ComposeNode,
+{
+    fn clone(&self) -> Self {
+        Self {
+            message: self.message.clone(),
+            attempt: self.attempt,
+        }
+    }
+}
+
+impl<N> Debug for BoundaryError<N>
+where
+    N: ComposeNode,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundaryError")
+            .field("message", &self.message)
+            .finish()
+    }
+}
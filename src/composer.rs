@@ -1,21 +1,37 @@
 use std::any::Any;
 use std::fmt::{Debug, Formatter};
+use std::mem;
+use std::sync::Arc;
 
-use generational_box::{AnyStorage, UnsyncStorage};
+use generational_box::AnyStorage;
 use slab::Slab;
 
+use crate::animation::AnyAnimation;
+use crate::applier::ApplierQueue;
+use crate::derived::DerivedEntry;
+use crate::effect::{Dispose, EffectSlot, PendingEffect};
+use crate::frame::{FrameClock, SystemFrameClock};
+use crate::key::KeyInterner;
+#[cfg(feature = "layout")]
+use crate::layout::LayoutEntry;
+use crate::local::LocalScope;
 use crate::map::{HashMapExt, HashSetExt, Map, Set};
+use crate::report::RecomposeReport;
+use crate::sender::Inbox;
+use crate::state::AnyPolicy;
+use crate::storage::{AnyBox, MaybeSendSync, Storage};
 use crate::subcompose::SubcompositionEntry;
-use crate::{Recomposer, Root, Scope, ScopeId, State, StateId};
+use crate::task::TaskSlot;
+use crate::{Applier, Loc, Recomposer, Root, Scope, ScopeId, State, StateId};
 
-pub trait Composable {
+pub trait Composable: MaybeSendSync {
     fn compose(&self) -> NodeKey;
     fn clone_box(&self) -> Box<dyn Composable>;
 }
 
 impl<T> Composable for T
 where
-    T: Fn() -> NodeKey + Clone + 'static,
+    T: Fn() -> NodeKey + Clone + MaybeSendSync + 'static,
 {
     fn compose(&self) -> NodeKey {
         self()
@@ -32,8 +48,8 @@ impl Clone for Box<dyn Composable> {
     }
 }
 
-pub trait ComposeNode: 'static {
-    type Context;
+pub trait ComposeNode: MaybeSendSync + 'static {
+    type Context: MaybeSendSync;
 }
 
 impl ComposeNode for () {
@@ -66,8 +82,36 @@ where
     }
 }
 
+impl<T> AnyData<T> for Box<dyn Any + Send + Sync>
+where
+    T: Send + Sync + 'static,
+{
+    #[inline(always)]
+    fn new(val: T) -> Self {
+        Box::new(val)
+    }
+
+    #[inline(always)]
+    fn value(&self) -> &T {
+        self.downcast_ref::<T>().unwrap()
+    }
+
+    #[inline(always)]
+    fn value_mut(&mut self) -> &mut T {
+        self.downcast_mut::<T>().unwrap()
+    }
+}
+
 pub type NodeKey = usize;
 
+// composer state put aside while composing a node away from the current position
+pub(crate) struct ComposeFrame {
+    child_idx_stack: Vec<usize>,
+    current_node_key: NodeKey,
+    current_locals: Option<Arc<LocalScope>>,
+    initialized: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Node<T> {
     pub scope_id: ScopeId,
@@ -97,17 +141,47 @@ where
     pub(crate) initialized: bool,
     pub(crate) root_node_key: NodeKey,
     pub(crate) composables: Map<NodeKey, Box<dyn Composable>>,
-    pub(crate) states: Map<NodeKey, Map<StateId, Box<dyn Any>>>,
+    pub(crate) node_args: Map<NodeKey, AnyBox>,
+    pub(crate) states: Map<NodeKey, Map<StateId, AnyBox>>,
+    pub(crate) state_policies: Map<StateId, AnyPolicy>,
     pub(crate) used_by: Map<StateId, Set<NodeKey>>,
     pub(crate) uses: Map<NodeKey, Set<StateId>>,
     pub(crate) current_node_key: NodeKey,
-    pub(crate) key_stack: Vec<usize>,
+    pub(crate) keys: KeyInterner,
+    pub(crate) key_stack: Vec<(usize, NodeKey)>,
+    pub(crate) keyed_siblings: Map<NodeKey, Set<usize>>,
     pub(crate) child_idx_stack: Vec<usize>,
     pub(crate) dirty_states: Set<StateId>,
     pub(crate) dirty_nodes: Set<NodeKey>,
+    pub(crate) detached_dirty: Set<NodeKey>,
+    // attached dirty nodes as (pre-order index, end of subtree, node), last first
+    pub(crate) dirty_order: Vec<(usize, usize, NodeKey)>,
     pub(crate) mount_nodes: Set<NodeKey>,
     pub(crate) unmount_nodes: Set<NodeKey>,
     pub(crate) subcompositions: Map<NodeKey, SubcompositionEntry>,
+    pub(crate) precomposed: Map<NodeKey, Set<NodeKey>>,
+    pub(crate) applier: ApplierQueue<N>,
+    pub(crate) effects: Map<NodeKey, Map<Loc, EffectSlot>>,
+    pub(crate) pending_effects: Vec<PendingEffect>,
+    pub(crate) pending_disposals: Vec<Dispose>,
+    pub(crate) tasks: Map<NodeKey, Map<Loc, TaskSlot>>,
+    pub(crate) current_locals: Option<Arc<LocalScope>>,
+    pub(crate) locals: Map<NodeKey, Arc<LocalScope>>,
+    pub(crate) derived: Map<NodeKey, Map<StateId, DerivedEntry>>,
+    pub(crate) derived_deps: Map<StateId, Set<StateId>>,
+    pub(crate) derived_inputs: Map<StateId, Set<StateId>>,
+    pub(crate) derived_stack: Vec<StateId>,
+    pub(crate) inbox: Arc<Inbox>,
+    pub(crate) frame_clock: Box<dyn FrameClock>,
+    pub(crate) frame_nanos: u64,
+    pub(crate) frame_advanced: bool,
+    pub(crate) frame_waiters: Set<NodeKey>,
+    pub(crate) animations: Map<StateId, Box<dyn AnyAnimation>>,
+    pub(crate) report: RecomposeReport,
+    pub(crate) recomposing: bool,
+    pub(crate) boundaries: Set<NodeKey>,
+    #[cfg(feature = "layout")]
+    pub(crate) layouts: Map<NodeKey, LayoutEntry<N>>,
 }
 
 impl<N> Composer<N>
@@ -121,17 +195,46 @@ where
             initialized: false,
             root_node_key: 0,
             composables: Map::new(),
+            node_args: Map::new(),
             states: Map::new(),
+            state_policies: Map::new(),
             used_by: Map::new(),
             uses: Map::new(),
             current_node_key: 0,
+            keys: KeyInterner::new(),
             key_stack: Vec::new(),
+            keyed_siblings: Map::new(),
             child_idx_stack: Vec::new(),
             dirty_states: Set::new(),
             dirty_nodes: Set::new(),
+            detached_dirty: Set::new(),
+            dirty_order: Vec::new(),
             mount_nodes: Set::new(),
             unmount_nodes: Set::new(),
             subcompositions: Map::new(),
+            precomposed: Map::new(),
+            applier: ApplierQueue::new(),
+            effects: Map::new(),
+            pending_effects: Vec::new(),
+            pending_disposals: Vec::new(),
+            tasks: Map::new(),
+            current_locals: None,
+            locals: Map::new(),
+            derived: Map::new(),
+            derived_deps: Map::new(),
+            derived_inputs: Map::new(),
+            derived_stack: Vec::new(),
+            inbox: Arc::default(),
+            frame_clock: Box::new(SystemFrameClock::new()),
+            frame_nanos: 0,
+            frame_advanced: false,
+            frame_waiters: Set::new(),
+            animations: Map::new(),
+            report: RecomposeReport::default(),
+            recomposing: false,
+            boundaries: Set::new(),
+            #[cfg(feature = "layout")]
+            layouts: Map::new(),
         }
     }
 
@@ -142,17 +245,46 @@ where
             initialized: false,
             root_node_key: 0,
             composables: Map::with_capacity(capacity),
+            node_args: Map::new(),
             states: Map::with_capacity(capacity),
+            state_policies: Map::new(),
             used_by: Map::with_capacity(capacity),
             uses: Map::with_capacity(capacity),
             current_node_key: 0,
             child_idx_stack: Vec::new(),
+            keys: KeyInterner::new(),
             key_stack: Vec::new(),
+            keyed_siblings: Map::new(),
             dirty_states: Set::new(),
             dirty_nodes: Set::new(),
+            detached_dirty: Set::new(),
+            dirty_order: Vec::new(),
             mount_nodes: Set::with_capacity(capacity),
             unmount_nodes: Set::new(),
             subcompositions: Map::with_capacity(capacity),
+            precomposed: Map::new(),
+            applier: ApplierQueue::new(),
+            effects: Map::new(),
+            pending_effects: Vec::new(),
+            pending_disposals: Vec::new(),
+            tasks: Map::new(),
+            current_locals: None,
+            locals: Map::new(),
+            derived: Map::new(),
+            derived_deps: Map::new(),
+            derived_inputs: Map::new(),
+            derived_stack: Vec::new(),
+            inbox: Arc::default(),
+            frame_clock: Box::new(SystemFrameClock::new()),
+            frame_nanos: 0,
+            frame_advanced: false,
+            frame_waiters: Set::new(),
+            animations: Map::new(),
+            report: RecomposeReport::default(),
+            recomposing: false,
+            boundaries: Set::new(),
+            #[cfg(feature = "layout")]
+            layouts: Map::new(),
         }
     }
 
@@ -162,7 +294,7 @@ where
     where
         R: Fn(Scope<Root, N>),
     {
-        let owner = UnsyncStorage::owner();
+        let owner = Storage::owner();
         let composer = owner.insert(Composer::with_capacity(context, 1024));
         let id = ScopeId::new();
         let scope = Scope::new(id, composer);
@@ -170,23 +302,24 @@ where
         let root_state = scope.use_state(|| {});
         root(scope);
         composer.write().end_root();
-        let mut c = composer.write();
-        c.initialized = true;
-        Recomposer {
+        composer.write().initialized = true;
+        let mut recomposer = Recomposer {
             owner,
             composer,
             root_state,
-        }
+        };
+        recomposer.apply_effects();
+        recomposer
     }
 
     #[track_caller]
     pub fn compose_with<R, F, T>(root: R, context: N::Context, state_fn: F) -> Recomposer<T, N>
     where
         R: Fn(Scope<Root, N>, State<T, N>),
-        F: Fn() -> T + 'static,
-        T: 'static,
+        F: Fn() -> T + MaybeSendSync + 'static,
+        T: MaybeSendSync + 'static,
     {
-        let owner = UnsyncStorage::owner();
+        let owner = Storage::owner();
         let composer = owner.insert(Composer::with_capacity(context, 1024));
         let id = ScopeId::new();
         let scope = Scope::new(id, composer);
@@ -194,13 +327,14 @@ where
         let root_state = scope.use_state(state_fn);
         root(scope, root_state);
         composer.write().end_root();
-        let mut c = composer.write();
-        c.initialized = true;
-        Recomposer {
+        composer.write().initialized = true;
+        let mut recomposer = Recomposer {
             owner,
             composer,
             root_state,
-        }
+        };
+        recomposer.apply_effects();
+        recomposer
     }
 
     #[inline(always)]
@@ -211,7 +345,7 @@ where
     #[inline(always)]
     pub(crate) fn start_root(&mut self, scope_id: ScopeId) {
         let parent_node_key = 0;
-        let node_key = self.nodes.insert(Node::new(scope_id, parent_node_key));
+        let node_key = self.insert_node(scope_id, parent_node_key);
         self.child_idx_stack.push(0);
         self.current_node_key = node_key;
     }
@@ -221,6 +355,10 @@ where
         let child_count = self.child_idx_stack.pop().unwrap();
         assert_eq!(1, child_count, "Root scope must have exactly one child");
         self.root_node_key = self.nodes[self.current_node_key].children[0];
+        // the first composition mounts everything, reports start with the first recompose
+        self.mount_nodes.clear();
+        self.keys.sweep();
+        self.report = RecomposeReport::default();
     }
 
     #[inline(always)]
@@ -234,25 +372,53 @@ where
                     let child_node = &mut self.nodes[child_key];
                     if child_node.scope_id == scope_id {
                         // reuse existing node
-                        self.current_node_key = child_key;
-                        self.mount_nodes.insert(child_key);
-                        self.child_idx_stack.push(0);
+                        self.reuse_node(child_key);
+                    } else if self.is_keyed(parent_node_key) {
+                        // keyed siblings are matched by scope id instead of position
+                        let siblings = &self.nodes[parent_node_key].children;
+                        let from = siblings[child_idx + 1..]
+                            .iter()
+                            .position(|key| self.nodes[*key].scope_id == scope_id)
+                            .map(|offset| child_idx + 1 + offset);
+                        if let Some(from) = from {
+                            // move existing node
+                            let siblings = &mut self.nodes[parent_node_key].children;
+                            let node_key = siblings.remove(from);
+                            siblings.insert(child_idx, node_key);
+                            self.applier
+                                .move_node(parent_node_key, from, child_idx, node_key);
+                            self.reuse_node(node_key);
+                        } else {
+                            // insert new node, the displaced ones may still match later
+                            let node_key = self.insert_node(scope_id, parent_node_key);
+                            self.nodes[parent_node_key]
+                                .children
+                                .insert(child_idx, node_key);
+                            self.mount_nodes.insert(node_key);
+                            self.report.mounted.push(node_key);
+                            self.record_locals(node_key);
+                            self.applier.insert_at(parent_node_key, child_idx, node_key);
+                            self.current_node_key = node_key;
+                            self.child_idx_stack.push(0);
+                        }
                     } else {
                         // replace existing node
-                        let node_key = self.nodes.insert(Node::new(scope_id, parent_node_key));
+                        let node_key = self.insert_node(scope_id, parent_node_key);
                         self.nodes[parent_node_key].children[child_idx] = node_key;
                         self.unmount_nodes.insert(child_key);
                         self.mount_nodes.insert(node_key);
+                        self.report.mounted.push(node_key);
+                        self.record_locals(node_key);
+                        self.applier.remove(parent_node_key, child_idx, child_key);
+                        self.applier.insert_at(parent_node_key, child_idx, node_key);
                         self.current_node_key = node_key;
                         self.child_idx_stack.push(0);
                     }
                 } else {
                     // append new node
-                    let node_key = self.nodes.insert(Node::new(scope_id, parent_node_key));
-                    self.nodes[parent_node_key].children.push(node_key);
-                    self.mount_nodes.insert(node_key);
-                    self.current_node_key = node_key;
-                    self.child_idx_stack.push(0);
+                    self.append_node(parent_node_key, scope_id);
+                    self.mount_nodes.insert(self.current_node_key);
+                    self.report.mounted.push(self.current_node_key);
                 }
             } else {
                 // recompose root
@@ -260,36 +426,647 @@ where
             }
         } else {
             // first compose
-            let node_key = self.nodes.insert(Node::new(scope_id, parent_node_key));
-            self.nodes[parent_node_key].children.push(node_key);
-            self.current_node_key = node_key;
-            self.child_idx_stack.push(0);
+            self.append_node(parent_node_key, scope_id);
+        }
+    }
+
+    // only the direct children of the node calling `Scope::key` are keyed
+    #[inline(always)]
+    fn is_keyed(&self, parent_node_key: NodeKey) -> bool {
+        self.key_stack
+            .last()
+            .is_some_and(|(_, node_key)| *node_key == parent_node_key)
+    }
+
+    // the key for a child of the current node, deeper descendants keep their own
+    #[inline(always)]
+    pub(crate) fn child_key(&self) -> Option<usize> {
+        self.key_stack
+            .last()
+            .filter(|(_, node_key)| *node_key == self.current_node_key)
+            .map(|(key, _)| *key)
+    }
+
+    #[inline(always)]
+    fn reuse_node(&mut self, node_key: NodeKey) {
+        self.record_locals(node_key);
+        self.current_node_key = node_key;
+        self.mount_nodes.insert(node_key);
+        self.child_idx_stack.push(0);
+    }
+
+    #[inline(always)]
+    fn append_node(&mut self, parent_node_key: NodeKey, scope_id: ScopeId) {
+        let node_key = self.insert_node(scope_id, parent_node_key);
+        let siblings = &mut self.nodes[parent_node_key].children;
+        let index = siblings.len();
+        siblings.push(node_key);
+        self.record_locals(node_key);
+        self.applier.insert_at(parent_node_key, index, node_key);
+        self.current_node_key = node_key;
+        self.child_idx_stack.push(0);
+    }
+
+    #[inline(always)]
+    fn insert_node(&mut self, scope_id: ScopeId, parent_node_key: NodeKey) -> NodeKey {
+        self.keys.retain(scope_id.key);
+        self.nodes.insert(Node::new(scope_id, parent_node_key))
+    }
+
+    #[inline(always)]
+    fn record_locals(&mut self, node_key: NodeKey) {
+        match self.current_locals.as_ref() {
+            Some(locals) => {
+                self.locals.insert(node_key, locals.clone());
+            }
+            None => {
+                self.locals.remove(&node_key);
+            }
         }
     }
 
     #[inline(always)]
     pub(crate) fn end_node(&mut self, parent_node_key: NodeKey) {
         let child_count = self.child_idx_stack.pop().unwrap();
-        let node = &mut self.nodes[self.current_node_key];
+        let node_key = self.current_node_key;
+        let node = &mut self.nodes[node_key];
         let old_child_count = node.children.len();
+        let mut unmount_nodes = Vec::new();
         if child_count < old_child_count {
-            let unmount_nodes = node.children.drain(child_count..);
-            self.unmount_nodes.extend(unmount_nodes);
+            unmount_nodes = node.children.drain(child_count..).collect::<Vec<_>>();
+            for (offset, child_key) in unmount_nodes.iter().enumerate().rev() {
+                self.applier
+                    .remove(node_key, child_count + offset, *child_key);
+            }
+        }
+        if let Some(entry) = self.subcompositions.get_mut(&node_key) {
+            let children = &self.nodes[node_key].children;
+            entry.end_pass(
+                children,
+                self.precomposed.get(&node_key),
+                &mut unmount_nodes,
+            );
         }
+        self.unmount_nodes.extend(unmount_nodes);
+        if !self.keyed_siblings.is_empty() {
+            self.keyed_siblings.remove(&node_key);
+        }
+        self.report.recomposed.push(node_key);
         if let Some(parent_child_count) = self.child_idx_stack.last_mut() {
             *parent_child_count += 1;
         }
         self.current_node_key = parent_node_key;
     }
 
+    // puts a slot node at the current child position, reattaching it if it was retained
+    pub(crate) fn attach_slot(&mut self, parent_node_key: NodeKey, node_key: NodeKey) {
+        let Some(child_idx) = self.child_idx_stack.last().copied() else {
+            return;
+        };
+        if !self.initialized
+            || self
+                .nodes
+                .get(node_key)
+                .is_none_or(|n| n.parent != parent_node_key)
+        {
+            return;
+        }
+        let siblings = &mut self.nodes[parent_node_key].children;
+        let child_idx = child_idx.min(siblings.len());
+        match siblings.iter().position(|key| *key == node_key) {
+            Some(from) if from > child_idx => {
+                siblings.remove(from);
+                siblings.insert(child_idx, node_key);
+                self.applier
+                    .move_node(parent_node_key, from, child_idx, node_key);
+            }
+            Some(_) => {}
+            None => {
+                siblings.insert(child_idx, node_key);
+                self.applier.insert_at(parent_node_key, child_idx, node_key);
+                if let Some(precomposed) = self.precomposed.get_mut(&parent_node_key) {
+                    precomposed.remove(&node_key);
+                }
+                self.redirty_detached(node_key);
+            }
+        }
+    }
+
+    // composes a slot of `host` off its child list, on the slot's node if it has one
+    pub(crate) fn begin_precompose(
+        &mut self,
+        host: NodeKey,
+        node_key: Option<NodeKey>,
+        scope_id: ScopeId,
+    ) -> ComposeFrame {
+        let frame = ComposeFrame {
+            child_idx_stack: mem::take(&mut self.child_idx_stack),
+            current_node_key: self.current_node_key,
+            current_locals: self.current_locals.clone(),
+            initialized: self.initialized,
+        };
+        let node_key = match node_key.filter(|key| self.nodes.contains(*key)) {
+            Some(node_key) => {
+                if !self.nodes[host].children.contains(&node_key) {
+                    self.precomposed.entry(host).or_default().insert(node_key);
+                }
+                self.redirty_detached(node_key);
+                node_key
+            }
+            None => {
+                let node_key = self.insert_node(scope_id, host);
+                self.record_locals(node_key);
+                self.report.mounted.push(node_key);
+                self.precomposed.entry(host).or_default().insert(node_key);
+                node_key
+            }
+        };
+        // the slot then composes like a node recomposed on its own
+        self.initialized = true;
+        self.current_node_key = node_key;
+        frame
+    }
+
+    pub(crate) fn end_precompose(&mut self, frame: ComposeFrame) {
+        self.child_idx_stack = frame.child_idx_stack;
+        self.current_node_key = frame.current_node_key;
+        self.current_locals = frame.current_locals;
+        self.initialized = frame.initialized;
+    }
+
+    // measuring composes the node's children like the node was recomposed on its own
+    #[cfg(feature = "layout")]
+    pub(crate) fn begin_measure(&mut self, node_key: NodeKey) -> ComposeFrame {
+        let frame = ComposeFrame {
+            child_idx_stack: mem::replace(&mut self.child_idx_stack, vec![0]),
+            current_node_key: self.current_node_key,
+            current_locals: self.current_locals.clone(),
+            initialized: self.initialized,
+        };
+        self.initialized = true;
+        self.current_node_key = node_key;
+        self.current_locals = self.locals.get(&node_key).cloned();
+        frame
+    }
+
+    // children the measure did not request are dropped as in `end_node`
+    #[cfg(feature = "layout")]
+    pub(crate) fn end_measure(&mut self, frame: ComposeFrame) {
+        let node_key = self.current_node_key;
+        self.end_node(node_key);
+        self.end_precompose(frame);
+    }
+
+    // at the current child position while the host composes, after its children otherwise
+    pub(crate) fn attach_precomposed(&mut self, host: NodeKey, node_key: NodeKey) {
+        let Some(precomposed) = self.precomposed.get_mut(&host) else {
+            return;
+        };
+        if !precomposed.remove(&node_key) {
+            return;
+        }
+        let composing = self.current_node_key == host && !self.child_idx_stack.is_empty();
+        let siblings = &mut self.nodes[host].children;
+        let index = match self.child_idx_stack.last_mut() {
+            Some(child_idx) if composing => {
+                let index = (*child_idx).min(siblings.len());
+                *child_idx = index + 1;
+                index
+            }
+            _ => siblings.len(),
+        };
+        siblings.insert(index, node_key);
+        self.applier.insert_at(host, index, node_key);
+        if composing {
+            self.redirty_detached(node_key);
+        }
+    }
+
+    // a node recomposed on its own changes the content of the slots it is nested in
+    pub(crate) fn touch_slots(&mut self, node_key: NodeKey) {
+        let mut child_key = node_key;
+        while child_key != self.root_node_key {
+            let Some(node) = self.nodes.get(child_key) else {
+                return;
+            };
+            let parent_key = node.parent;
+            if parent_key == child_key {
+                return;
+            }
+            if child_key != node_key {
+                if let Some(entry) = self.subcompositions.get_mut(&parent_key) {
+                    let slot_id = entry
+                        .slots
+                        .iter()
+                        .find(|(_, slot)| slot.node_key == Some(child_key))
+                        .map(|(slot_id, _)| *slot_id);
+                    if let Some(slot_id) = slot_id {
+                        entry.touch(slot_id);
+                    }
+                }
+            }
+            child_key = parent_key;
+        }
+    }
+
+    // dirty nodes dropped while detached from the tree, for the subtree under `node_key`
+    fn redirty_detached(&mut self, node_key: NodeKey) {
+        let nodes = self
+            .detached_dirty
+            .iter()
+            .copied()
+            .filter(|key| self.is_within(*key, node_key))
+            .collect::<Vec<_>>();
+        for key in nodes {
+            self.detached_dirty.remove(&key);
+            self.dirty_nodes.insert(key);
+        }
+    }
+
+    fn is_within(&self, mut node_key: NodeKey, ancestor: NodeKey) -> bool {
+        loop {
+            if node_key == ancestor {
+                return true;
+            }
+            if node_key == self.root_node_key {
+                return false;
+            }
+            match self.nodes.get(node_key) {
+                Some(node) if node.parent != node_key => node_key = node.parent,
+                _ => return false,
+            }
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn skip_node(&mut self, parent_node_key: NodeKey) {
         let _ = self.child_idx_stack.pop().unwrap();
+        self.report.skipped.push(self.current_node_key);
         if let Some(parent_child_count) = self.child_idx_stack.last_mut() {
             *parent_child_count += 1;
         }
         self.current_node_key = parent_node_key;
     }
+
+    // layout nodes compose their children when measured, composing one keeps them as they are
+    #[cfg(feature = "layout")]
+    #[inline(always)]
+    pub(crate) fn end_layout_node(&mut self, parent_node_key: NodeKey) {
+        let _ = self.child_idx_stack.pop().unwrap();
+        self.report.recomposed.push(self.current_node_key);
+        if let Some(parent_child_count) = self.child_idx_stack.last_mut() {
+            *parent_child_count += 1;
+        }
+        self.current_node_key = parent_node_key;
+    }
+
+    // the dirty node first in tree order, dirty nodes no longer attached to the tree are discarded
+    pub(crate) fn next_dirty_node(&mut self) -> Option<NodeKey> {
+        while let Some((_, _, node_key)) = self.dirty_order.last() {
+            if self.dirty_nodes.contains(node_key) {
+                break;
+            }
+            self.dirty_order.pop();
+        }
+        // nodes dirtied since the order was built, or left dirty under a node that recomposed
+        if self.dirty_nodes.len() != self.dirty_order.len() {
+            self.sort_dirty_nodes();
+        }
+        self.dirty_order.last().map(|(_, _, node_key)| *node_key)
+    }
+
+    // the dirty nodes of its subtree were covered by its re-execution
+    pub(crate) fn end_dirty_node(&mut self, node_key: NodeKey) {
+        self.dirty_nodes.remove(&node_key);
+        let Some((_, end, front)) = self.dirty_order.last().copied() else {
+            return;
+        };
+        if front != node_key {
+            return;
+        }
+        self.dirty_order.pop();
+        while self
+            .dirty_order
+            .last()
+            .is_some_and(|(index, _, _)| *index < end)
+        {
+            self.dirty_order.pop();
+        }
+    }
+
+    // one pre-order walk numbers the nodes, a subtree spans the indices up to its end
+    fn sort_dirty_nodes(&mut self) {
+        self.dirty_order.clear();
+        if self.dirty_nodes.is_empty() {
+            return;
+        }
+        let mut index = 0;
+        let mut open: Vec<usize> = Vec::new();
+        let mut stack = vec![(self.root_node_key, false)];
+        while let Some((node_key, exit)) = stack.pop() {
+            let is_dirty = self.dirty_nodes.contains(&node_key);
+            if exit {
+                if is_dirty {
+                    let pos = open.pop().unwrap();
+                    self.dirty_order[pos].1 = index;
+                }
+                continue;
+            }
+            if is_dirty {
+                open.push(self.dirty_order.len());
+                self.dirty_order.push((index, 0, node_key));
+            }
+            index += 1;
+            stack.push((node_key, true));
+            if let Some(node) = self.nodes.get(node_key) {
+                stack.extend(node.children.iter().rev().map(|child| (*child, false)));
+            }
+        }
+        if self.dirty_order.len() != self.dirty_nodes.len() {
+            let attached = self
+                .dirty_order
+                .iter()
+                .map(|(_, _, node_key)| *node_key)
+                .collect::<Set<_>>();
+            let detached = self
+                .dirty_nodes
+                .iter()
+                .copied()
+                .filter(|node_key| !attached.contains(node_key))
+                .collect::<Vec<_>>();
+            for node_key in detached {
+                self.dirty_nodes.remove(&node_key);
+                self.detached_dirty.insert(node_key);
+            }
+        }
+        // the first in tree order is taken from the back
+        self.dirty_order.reverse();
+    }
+
+    #[inline(always)]
+    pub(crate) fn derived_entry(&mut self, id: StateId) -> &mut DerivedEntry {
+        self.derived
+            .get_mut(&id.node_key)
+            .unwrap()
+            .get_mut(&id)
+            .unwrap()
+    }
+
+    #[inline(always)]
+    pub(crate) fn track_read(&mut self, state_id: StateId) {
+        if let Some(derived_id) = self.derived_stack.last().copied() {
+            self.derived_deps
+                .entry(state_id)
+                .or_default()
+                .insert(derived_id);
+            self.derived_inputs
+                .entry(derived_id)
+                .or_default()
+                .insert(state_id);
+        } else {
+            let current_node_key = self.current_node_key;
+            self.used_by
+                .entry(state_id)
+                .or_default()
+                .insert(current_node_key);
+            self.uses
+                .entry(current_node_key)
+                .or_default()
+                .insert(state_id);
+        }
+    }
+
+    pub(crate) fn apply_inbox(&mut self) {
+        for (state_id, value) in self.inbox.take() {
+            // the owning node may have been disposed since the update was sent
+            let Some(val) = self
+                .states
+                .get_mut(&state_id.node_key)
+                .and_then(|scope_states| scope_states.get_mut(&state_id))
+            else {
+                continue;
+            };
+            if let Some(equivalent) = self.state_policies.get(&state_id) {
+                if equivalent(val.as_ref(), value.as_ref()) {
+                    continue;
+                }
+            }
+            *val = value;
+            self.dirty_states.insert(state_id);
+        }
+    }
+
+    pub(crate) fn step_animations(&mut self) {
+        let frame_nanos = self.frame_nanos;
+        for (state_id, animation) in self.animations.iter_mut() {
+            if !animation.is_running() {
+                continue;
+            }
+            let value = animation.step(frame_nanos);
+            if let Some(val) = self
+                .states
+                .get_mut(&state_id.node_key)
+                .and_then(|scope_states| scope_states.get_mut(state_id))
+            {
+                *val = value;
+                self.dirty_states.insert(*state_id);
+            }
+        }
+    }
+
+    pub(crate) fn set_applier(&mut self, mut applier: Box<dyn Applier<N>>) {
+        // replay the current tree so the backend starts from the same state
+        applier.on_begin();
+        let root_parent = self.nodes[self.root_node_key].parent;
+        let mut stack = vec![(root_parent, 0, self.root_node_key)];
+        while let Some((parent, index, node_key)) = stack.pop() {
+            let node = &self.nodes[node_key];
+            applier.insert_at(parent, index, node_key);
+            if let Some(data) = node.data.as_ref() {
+                applier.update(node_key, data);
+            }
+            for (index, child_key) in node.children.iter().enumerate().rev() {
+                stack.push((node_key, index, *child_key));
+            }
+        }
+        applier.on_end();
+        self.applier.set(applier);
+    }
+
+    // sends what the pass changed, once it completed
+    #[inline(always)]
+    pub(crate) fn end_apply(&mut self) {
+        self.applier.flush(&self.nodes);
+    }
+
+    // the closest error boundary above the node
+    pub(crate) fn enclosing_boundary(&self, node_key: NodeKey) -> Option<NodeKey> {
+        if self.boundaries.is_empty() {
+            return None;
+        }
+        let mut node_key = node_key;
+        while node_key != self.root_node_key {
+            node_key = self.nodes.get(node_key)?.parent;
+            if self.boundaries.contains(&node_key) {
+                return Some(node_key);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn boundary_snapshot(&self) -> BoundarySnapshot {
+        BoundarySnapshot {
+            child_idx_depth: self.child_idx_stack.len(),
+            key_depth: self.key_stack.len(),
+            derived_depth: self.derived_stack.len(),
+            current_locals: self.current_locals.clone(),
+            unmount_nodes: self.unmount_nodes.clone(),
+            recomposed: self.report.recomposed.len(),
+            skipped: self.report.skipped.len(),
+            mounted: self.report.mounted.len(),
+            updated: self.report.updated.len(),
+        }
+    }
+
+    // drops whatever the failed content left under the boundary, old and new nodes alike,
+    // so the fallback starts from an empty node
+    pub(crate) fn rollback_boundary(&mut self, node_key: NodeKey, snapshot: BoundarySnapshot) {
+        self.child_idx_stack.truncate(snapshot.child_idx_depth);
+        if let Some(child_idx) = self.child_idx_stack.last_mut() {
+            *child_idx = 0;
+        }
+        self.key_stack.truncate(snapshot.key_depth);
+        self.derived_stack.truncate(snapshot.derived_depth);
+        self.current_locals = snapshot.current_locals;
+        self.current_node_key = node_key;
+        self.keyed_siblings.remove(&node_key);
+
+        let children = mem::take(&mut self.nodes[node_key].children);
+        for (index, child_key) in children.iter().enumerate().rev() {
+            self.applier.remove(node_key, index, *child_key);
+        }
+        let mut stale = children;
+        stale.extend(self.unmount_nodes.difference(&snapshot.unmount_nodes));
+        let created = self.report.mounted.split_off(snapshot.mounted);
+        self.report.recomposed.truncate(snapshot.recomposed);
+        self.report.skipped.truncate(snapshot.skipped);
+        self.report.updated.truncate(snapshot.updated);
+        let unmounted = self.report.unmounted.len();
+        for key in stale {
+            self.unmount_nodes.remove(&key);
+            self.unmount_node(key);
+        }
+        let disposed = self.report.unmounted.split_off(unmounted);
+        self.report
+            .unmounted
+            .extend(disposed.into_iter().filter(|key| !created.contains(key)));
+        // clean up before the fallback gets a chance to reuse the freed keys
+        let nodes = &self.nodes;
+        self.mount_nodes.retain(|key| nodes.contains(*key));
+        self.keyed_siblings.retain(|key, _| nodes.contains(*key));
+        self.pending_effects.retain(|e| nodes.contains(e.node_key));
+    }
+
+    pub(crate) fn unmount_stale_nodes(&mut self) {
+        let unmount_nodes = self
+            .unmount_nodes
+            .difference(&self.mount_nodes)
+            .cloned()
+            .collect::<Vec<_>>();
+        for n in unmount_nodes {
+            self.unmount_node(n);
+        }
+        let nodes = &self.nodes;
+        self.report.mounted.retain(|key| nodes.contains(*key));
+        self.mount_nodes.clear();
+        self.unmount_nodes.clear();
+        self.keys.sweep();
+    }
+
+    pub(crate) fn unmount_node(&mut self, node_key: NodeKey) {
+        // reversed pre-order visits every child before its parent
+        let mut stack = vec![node_key];
+        let mut subtree = Vec::new();
+        while let Some(key) = stack.pop() {
+            if let Some(node) = self.nodes.get(key) {
+                subtree.push(key);
+                stack.extend(node.children.iter().copied());
+                if let Some(entry) = self.subcompositions.get(&key) {
+                    stack.extend(entry.retained_nodes(&node.children));
+                }
+            }
+        }
+        for key in subtree.into_iter().rev() {
+            self.dispose_node(key);
+            self.report.unmounted.push(key);
+        }
+    }
+
+    fn dispose_node(&mut self, node_key: NodeKey) {
+        let node = self.nodes.remove(node_key);
+        self.keys.release(node.scope_id.key);
+        self.subcompositions.remove(&node_key);
+        self.precomposed.remove(&node_key);
+        if let Some(precomposed) = self.precomposed.get_mut(&node.parent) {
+            precomposed.remove(&node_key);
+        }
+        if let Some(entry) = self.subcompositions.get_mut(&node.parent) {
+            for slot in entry.slots.values_mut() {
+                if slot.node_key == Some(node_key) {
+                    slot.node_key = None;
+                }
+            }
+        }
+        self.composables.remove(&node_key);
+        self.node_args.remove(&node_key);
+        self.dirty_nodes.remove(&node_key);
+        self.detached_dirty.remove(&node_key);
+        self.locals.remove(&node_key);
+        if let Some(effects) = self.effects.remove(&node_key) {
+            let disposals = effects.into_values().filter_map(|e| e.dispose);
+            self.pending_disposals.extend(disposals);
+        }
+        self.tasks.remove(&node_key);
+        self.frame_waiters.remove(&node_key);
+        self.boundaries.remove(&node_key);
+        #[cfg(feature = "layout")]
+        self.layouts.remove(&node_key);
+        self.derived.remove(&node_key);
+        if let Some(node_states) = self.states.remove(&node_key) {
+            for state in node_states.keys() {
+                self.used_by.remove(state);
+                self.state_policies.remove(state);
+                self.animations.remove(state);
+                self.derived_deps.remove(state);
+                if let Some(inputs) = self.derived_inputs.remove(state) {
+                    for input in inputs {
+                        if let Some(dependents) = self.derived_deps.get_mut(&input) {
+                            dependents.remove(state);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(use_states) = self.uses.remove(&node_key) {
+            for state in use_states {
+                if let Some(used_by) = self.used_by.get_mut(&state) {
+                    used_by.remove(&node_key);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct BoundarySnapshot {
+    child_idx_depth: usize,
+    key_depth: usize,
+    derived_depth: usize,
+    current_locals: Option<Arc<LocalScope>>,
+    unmount_nodes: Set<NodeKey>,
+    recomposed: usize,
+    skipped: usize,
+    mounted: usize,
+    updated: usize,
 }
 
 impl<N> Debug for Composer<N>
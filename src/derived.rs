@@ -0,0 +1,198 @@
+use std::any::Any;
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+use generational_box::GenerationalBox;
+
+use crate::map::{HashSetExt, Set};
+use crate::storage::{AnyBox, DynFn, MaybeSendSync, Storage};
+use crate::{ComposeNode, Composer, StateId};
+
+pub(crate) struct DerivedEntry {
+    pub compute: Arc<DynFn<AnyBox>>,
+    pub eq: fn(&dyn Any, &dyn Any) -> bool,
+    pub stale: bool,
+    pub changed: bool,
+}
+
+pub struct DerivedState<T, N>
+where
+    N: ComposeNode,
+{
+    pub id: StateId,
+    composer: GenerationalBox<Composer<N>, Storage>,
+    ty: PhantomData<T>,
+}
+
+impl<T, N> DerivedState<T, N>
+where
+    T: PartialEq + MaybeSendSync + 'static,
+    N: ComposeNode,
+{
+    #[inline(always)]
+    pub(crate) fn new(id: StateId, composer: GenerationalBox<Composer<N>, Storage>) -> Self {
+        Self {
+            id,
+            composer,
+            ty: PhantomData,
+        }
+    }
+
+    pub fn with<F, U>(&self, func: F) -> U
+    where
+        F: Fn(&T) -> U,
+    {
+        self.composer.write().track_read(self.id);
+        self.with_untracked(func)
+    }
+
+    pub fn with_untracked<F, U>(&self, func: F) -> U
+    where
+        F: Fn(&T) -> U,
+    {
+        let is_stale = self.composer.write().derived_entry(self.id).stale;
+        if is_stale {
+            refresh(self.composer, self.id);
+        }
+        let c = self.composer.read();
+        let scope_states = c.states.get(&self.id.node_key).unwrap();
+        let any_state = scope_states.get(&self.id).unwrap();
+        let state = any_state.downcast_ref::<T>().unwrap();
+        func(state)
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with_untracked(T::clone)
+    }
+}
+
+impl<T, N> Debug for DerivedState<T, N>
+where
+    N: ComposeNode,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedState")
+            .field("id", &self.id)
+            .field("ty", &self.ty)
+            .finish()
+    }
+}
+
+impl<T, N> Clone for DerivedState<T, N>
+where
+    N: ComposeNode,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, N> Copy for DerivedState<T, N> where N: ComposeNode {}
+
+#[inline(always)]
+pub(crate) fn eq_any<T>(a: &dyn Any, b: &dyn Any) -> bool
+where
+    T: PartialEq + 'static,
+{
+    a.downcast_ref::<T>() == b.downcast_ref::<T>()
+}
+
+// recompute a derived state, recording the states it reads as its new inputs
+pub(crate) fn refresh<N>(composer: GenerationalBox<Composer<N>, Storage>, id: StateId)
+where
+    N: ComposeNode,
+{
+    let compute = {
+        let mut c = composer.write();
+        if let Some(inputs) = c.derived_inputs.remove(&id) {
+            for input in inputs {
+                if let Some(dependents) = c.derived_deps.get_mut(&input) {
+                    dependents.remove(&id);
+                }
+            }
+        }
+        c.derived_stack.push(id);
+        c.derived_entry(id).compute.clone()
+    };
+    let value = compute();
+    let mut c = composer.write();
+    let c = c.deref_mut();
+    c.derived_stack.pop();
+    let entry = c
+        .derived
+        .get_mut(&id.node_key)
+        .unwrap()
+        .get_mut(&id)
+        .unwrap();
+    let scope_states = c.states.entry(id.node_key).or_default();
+    entry.changed = match scope_states.get(&id) {
+        Some(prev) => !(entry.eq)(prev.as_ref(), value.as_ref()),
+        None => true,
+    };
+    entry.stale = false;
+    scope_states.insert(id, value);
+}
+
+// mark derived states depending on the dirty states as stale, then recompute the
+// ones read by nodes and invalidate those nodes only if the value changed
+pub(crate) fn invalidate<N>(
+    composer: GenerationalBox<Composer<N>, Storage>,
+    dirty_states: &[StateId],
+) where
+    N: ComposeNode,
+{
+    let affected = {
+        let mut c = composer.write();
+        let c = c.deref_mut();
+        let mut affected = Vec::new();
+        let mut visited = Set::new();
+        let mut stack = dirty_states.to_vec();
+        while let Some(state_id) = stack.pop() {
+            let Some(dependents) = c.derived_deps.get(&state_id).cloned() else {
+                continue;
+            };
+            for derived_id in dependents {
+                if visited.insert(derived_id) {
+                    let entry = c.derived_entry(derived_id);
+                    entry.stale = true;
+                    entry.changed = false;
+                    affected.push(derived_id);
+                    stack.push(derived_id);
+                }
+            }
+        }
+        affected
+    };
+    for derived_id in affected {
+        let (is_stale, is_read) = {
+            let mut c = composer.write();
+            let is_read = c.used_by.get(&derived_id).is_some_and(|r| !r.is_empty());
+            (c.derived_entry(derived_id).stale, is_read)
+        };
+        if !is_read {
+            continue;
+        }
+        if is_stale {
+            refresh(composer, derived_id);
+        }
+        let mut c = composer.write();
+        let c = c.deref_mut();
+        if c.derived_entry(derived_id).changed {
+            if let Some(readers) = c.used_by.get(&derived_id) {
+                c.dirty_nodes.extend(readers);
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::storage::{AnyBox, DynFnOnce};
+use crate::{Loc, NodeKey};
+
+pub(crate) type Dispose = Box<DynFnOnce<()>>;
+
+pub(crate) struct EffectSlot {
+    pub keys: AnyBox,
+    pub dispose: Option<Dispose>,
+}
+
+pub(crate) struct PendingEffect {
+    pub node_key: NodeKey,
+    pub loc: Loc,
+    // side effects have no keys and leave no slot behind
+    pub keys: Option<AnyBox>,
+    pub effect: Box<DynFnOnce<Option<Dispose>>>,
+}
+
+impl PendingEffect {
+    #[inline(always)]
+    pub fn is_same_slot(&self, node_key: NodeKey, loc: Loc) -> bool {
+        self.keys.is_some() && self.node_key == node_key && self.loc == loc
+    }
+}
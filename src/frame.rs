@@ -0,0 +1,65 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::MaybeSendSync;
+
+pub trait FrameClock: MaybeSendSync {
+    /// Monotonic time of the next frame in nanoseconds.
+    fn frame_nanos(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SystemFrameClock {
+    start: Instant,
+}
+
+impl SystemFrameClock {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemFrameClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameClock for SystemFrameClock {
+    fn frame_nanos(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+}
+
+/// A clock advanced by hand, clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualFrameClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualFrameClock {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline(always)]
+    pub fn set(&self, nanos: u64) {
+        self.nanos.fetch_max(nanos, Ordering::SeqCst);
+    }
+
+    #[inline(always)]
+    pub fn advance(&self, nanos: u64) {
+        self.nanos.fetch_add(nanos, Ordering::SeqCst);
+    }
+}
+
+impl FrameClock for ManualFrameClock {
+    fn frame_nanos(&self) -> u64 {
+        self.nanos.load(Ordering::SeqCst)
+    }
+}
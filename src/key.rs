@@ -0,0 +1,113 @@
+use std::any::{Any, TypeId};
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+use rustc_hash::FxHasher;
+
+use crate::map::{HashMapExt, Map};
+use crate::MaybeSendSync;
+
+pub(crate) trait DynKey: MaybeSendSync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<K> DynKey for K
+where
+    K: Hash + Eq + MaybeSendSync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// keys of different types never compare equal, so their type is hashed too
+pub(crate) fn hash_key<K>(key: &K) -> u64
+where
+    K: Hash + 'static,
+{
+    let mut hasher = FxHasher::default();
+    TypeId::of::<K>().hash(&mut hasher);
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+type Bucket = Vec<(Box<dyn DynKey>, usize)>;
+
+// maps arbitrary keys to unique ids, hash collisions are resolved by comparing the keys;
+// an id lives as long as the nodes keyed by it
+pub(crate) struct KeyInterner {
+    buckets: Map<u64, Bucket>,
+    // id -> (key hash, nodes keyed by it)
+    refs: Map<usize, (u64, usize)>,
+    // interned since the last sweep, dropped then unless a node took them
+    unused: Vec<usize>,
+    next_id: usize,
+}
+
+impl KeyInterner {
+    pub fn new() -> Self {
+        Self {
+            buckets: Map::new(),
+            refs: Map::new(),
+            unused: Vec::new(),
+            // 0 is left for unkeyed scopes
+            next_id: 1,
+        }
+    }
+
+    pub fn intern<K>(&mut self, key: K) -> usize
+    where
+        K: Hash + Eq + MaybeSendSync + 'static,
+    {
+        let hash = hash_key(&key);
+        let bucket = self.buckets.entry(hash).or_default();
+        let found = bucket
+            .iter()
+            .find(|(k, _)| k.as_any().downcast_ref::<K>() == Some(&key));
+        if let Some((_, id)) = found {
+            return *id;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        bucket.push((Box::new(key), id));
+        self.refs.insert(id, (hash, 0));
+        self.unused.push(id);
+        id
+    }
+
+    #[inline(always)]
+    pub fn retain(&mut self, id: usize) {
+        if let Some((_, count)) = self.refs.get_mut(&id) {
+            *count += 1;
+        }
+    }
+
+    pub fn release(&mut self, id: usize) {
+        if let Some((_, count)) = self.refs.get_mut(&id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.remove(id);
+            }
+        }
+    }
+
+    pub fn sweep(&mut self) {
+        for id in mem::take(&mut self.unused) {
+            if self.refs.get(&id).is_some_and(|(_, count)| *count == 0) {
+                self.remove(id);
+            }
+        }
+    }
+
+    fn remove(&mut self, id: usize) {
+        let Some((hash, _)) = self.refs.remove(&id) else {
+            return;
+        };
+        if let Some(bucket) = self.buckets.get_mut(&hash) {
+            bucket.retain(|(_, key_id)| *key_id != id);
+            if bucket.is_empty() {
+                self.buckets.remove(&hash);
+            }
+        }
+    }
+}
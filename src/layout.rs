@@ -0,0 +1,422 @@
+use std::hash::Hash;
+use std::mem;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::time::Instant;
+
+use generational_box::GenerationalBox;
+
+use crate::composer::NodeKey;
+use crate::scope::update_node;
+use crate::storage::{MaybeSendSync, Storage};
+use crate::subcompose::{SlotRetention, Subcomposition};
+use crate::{ComposeNode, Composer, RecomposeReport, Recomposer, Scope};
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Size {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Size {
+    pub const ZERO: Size = Size::new(0.0, 0.0);
+
+    #[inline(always)]
+    pub const fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Point {
+    pub const ZERO: Point = Point::new(0.0, 0.0);
+
+    #[inline(always)]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// The range of sizes a node may take, the max bounds can be infinite.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Constraints {
+    pub min_width: f32,
+    pub max_width: f32,
+    pub min_height: f32,
+    pub max_height: f32,
+}
+
+impl Constraints {
+    #[inline(always)]
+    pub fn new(min_width: f32, max_width: f32, min_height: f32, max_height: f32) -> Self {
+        Self {
+            min_width,
+            max_width,
+            min_height,
+            max_height,
+        }
+    }
+
+    #[inline(always)]
+    pub fn fixed(width: f32, height: f32) -> Self {
+        Self::new(width, width, height, height)
+    }
+
+    #[inline(always)]
+    pub fn loose(max_width: f32, max_height: f32) -> Self {
+        Self::new(0.0, max_width, 0.0, max_height)
+    }
+
+    #[inline(always)]
+    pub fn unbounded() -> Self {
+        Self::loose(f32::INFINITY, f32::INFINITY)
+    }
+
+    #[inline(always)]
+    pub fn loosen(&self) -> Self {
+        Self::loose(self.max_width, self.max_height)
+    }
+
+    /// The size closest to `size` within the bounds.
+    #[inline(always)]
+    pub fn constrain(&self, size: Size) -> Size {
+        Size::new(
+            size.width.min(self.max_width).max(self.min_width),
+            size.height.min(self.max_height).max(self.min_height),
+        )
+    }
+}
+
+/// Where a node was placed in the last layout pass, relative to the layout node placing it.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Placement {
+    pub offset: Point,
+    pub size: Size,
+}
+
+pub trait Layout<N>: MaybeSendSync + 'static
+where
+    N: ComposeNode,
+{
+    /// Subcomposes and measures the children it needs, places those it shows and returns
+    /// its own size, which is then coerced into `constraints`.
+    fn measure(&self, scope: &mut MeasureScope<N>, constraints: Constraints) -> Size;
+}
+
+impl<N, F> Layout<N> for F
+where
+    N: ComposeNode,
+    F: Fn(&mut MeasureScope<N>, Constraints) -> Size + MaybeSendSync + 'static,
+{
+    fn measure(&self, scope: &mut MeasureScope<N>, constraints: Constraints) -> Size {
+        self(scope, constraints)
+    }
+}
+
+pub(crate) struct LayoutEntry<N>
+where
+    N: ComposeNode,
+{
+    layout: Arc<dyn Layout<N>>,
+    placement: Option<Placement>,
+}
+
+pub struct MeasureScope<N>
+where
+    N: ComposeNode,
+{
+    composer: GenerationalBox<Composer<N>, Storage>,
+    node_key: NodeKey,
+    // created by the first request, the entry then outlives the scope
+    host: Option<Subcomposition<N>>,
+}
+
+impl<N> MeasureScope<N>
+where
+    N: ComposeNode,
+{
+    #[inline(always)]
+    pub fn node_key(&self) -> NodeKey {
+        self.node_key
+    }
+
+    /// Composes a slot of this node and returns the layout nodes it emitted, in tree order.
+    #[track_caller]
+    pub fn subcompose<T, F>(
+        &mut self,
+        slot_key: impl Hash + Eq + Clone + MaybeSendSync + 'static,
+        content: F,
+    ) -> Vec<Measurable<N>>
+    where
+        T: 'static,
+        F: Fn(Scope<T, N>) + Clone + MaybeSendSync + 'static,
+    {
+        let (composer, node_key) = (self.composer, self.node_key);
+        let host = self
+            .host
+            .get_or_insert_with(|| Subcomposition::new(node_key, composer));
+        let handle = host.subcompose::<T, _, _>(slot_key, (), move |slot| content(slot.scope()));
+        let c = self.composer.read();
+        layout_nodes(&c, handle.node_key())
+            .into_iter()
+            .map(|node_key| Measurable { composer, node_key })
+            .collect()
+    }
+
+    /// Sets how long slots this node stopped requesting keep their subtree.
+    pub fn set_retention(&mut self, retention: SlotRetention) {
+        let (composer, node_key) = (self.composer, self.node_key);
+        self.host
+            .get_or_insert_with(|| Subcomposition::new(node_key, composer))
+            .set_retention(retention);
+    }
+
+    pub fn place(&mut self, placeable: Placeable, x: f32, y: f32) {
+        let mut c = self.composer.write();
+        if let Some(entry) = c.layouts.get_mut(&placeable.node_key) {
+            entry.placement = Some(Placement {
+                offset: Point::new(x, y),
+                size: placeable.size,
+            });
+        }
+    }
+}
+
+pub struct Measurable<N>
+where
+    N: ComposeNode,
+{
+    composer: GenerationalBox<Composer<N>, Storage>,
+    node_key: NodeKey,
+}
+
+impl<N> Clone for Measurable<N>
+where
+    N: ComposeNode,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<N> Copy for Measurable<N> where N: ComposeNode {}
+
+impl<N> Measurable<N>
+where
+    N: ComposeNode,
+{
+    #[inline(always)]
+    pub fn node_key(&self) -> NodeKey {
+        self.node_key
+    }
+
+    /// Can be called more than once, the placement uses the size the node is placed with.
+    pub fn measure(&self, constraints: Constraints) -> Placeable {
+        Placeable {
+            node_key: self.node_key,
+            size: measure_node(self.composer, self.node_key, constraints),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Placeable {
+    node_key: NodeKey,
+    size: Size,
+}
+
+impl Placeable {
+    #[inline(always)]
+    pub fn node_key(&self) -> NodeKey {
+        self.node_key
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    #[inline(always)]
+    pub fn width(&self) -> f32 {
+        self.size.width
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> f32 {
+        self.size.height
+    }
+}
+
+fn measure_node<N>(
+    composer: GenerationalBox<Composer<N>, Storage>,
+    node_key: NodeKey,
+    constraints: Constraints,
+) -> Size
+where
+    N: ComposeNode,
+{
+    let (layout, frame) = {
+        let mut c = composer.write();
+        let Some(entry) = c.layouts.get(&node_key) else {
+            return constraints.constrain(Size::ZERO);
+        };
+        let layout = entry.layout.clone();
+        (layout, c.begin_measure(node_key))
+    };
+    let mut scope = MeasureScope {
+        composer,
+        node_key,
+        host: None,
+    };
+    let size = layout.measure(&mut scope, constraints);
+    composer.write().end_measure(frame);
+    constraints.constrain(size)
+}
+
+// the outermost layout nodes under `node_key`, looking through nodes of other kinds
+fn layout_nodes<N>(c: &Composer<N>, node_key: NodeKey) -> Vec<NodeKey>
+where
+    N: ComposeNode,
+{
+    let mut found = Vec::new();
+    let mut stack = vec![node_key];
+    while let Some(key) = stack.pop() {
+        if c.layouts.contains_key(&key) {
+            found.push(key);
+        } else if let Some(node) = c.nodes.get(key) {
+            stack.extend(node.children.iter().rev().copied());
+        }
+    }
+    found
+}
+
+impl<S, N> Scope<S, N>
+where
+    S: 'static,
+    N: ComposeNode,
+{
+    // like `create_node`, but the children are subcomposed by `layout` during the layout pass
+    pub fn layout<T, L, I, A, F, U>(
+        &self,
+        child_scope: Scope<T, N>,
+        layout: L,
+        input: I,
+        factory: F,
+        update: U,
+    ) where
+        T: 'static,
+        L: Layout<N>,
+        I: Fn() -> A + Clone + MaybeSendSync + 'static,
+        A: MaybeSendSync + 'static,
+        F: Fn(A, &mut N::Context) -> N + Clone + MaybeSendSync + 'static,
+        U: Fn(&mut N, A, &mut N::Context) + Clone + MaybeSendSync + 'static,
+    {
+        let layout: Arc<dyn Layout<N>> = Arc::new(layout);
+        let parent_scope = *self;
+        let composable = move || {
+            let mut current_scope = child_scope;
+            let mut c = parent_scope.composer.write();
+            if let Some(key) = c.child_key() {
+                current_scope.set_key(key);
+            }
+            let parent_node_key = c.current_node_key;
+            c.start_node(parent_node_key, current_scope.id);
+            let current_node_key = c.current_node_key;
+            // the layout is swapped even when skipped, measuring runs on every pass anyway
+            match c.layouts.get_mut(&current_node_key) {
+                Some(entry) => entry.layout = layout.clone(),
+                None => {
+                    let entry = LayoutEntry {
+                        layout: layout.clone(),
+                        placement: None,
+                    };
+                    c.layouts.insert(current_node_key, entry);
+                }
+            }
+            let is_visited = c.composables.contains_key(&current_node_key);
+            let is_dirty = c.dirty_nodes.contains(&current_node_key);
+            if !is_dirty && is_visited {
+                c.skip_node(parent_node_key);
+                return current_node_key;
+            }
+            drop(c);
+            let args = input();
+            let mut c = parent_scope.composer.write();
+            let c = c.deref_mut();
+            if update_node(
+                current_node_key,
+                &mut c.context,
+                &mut c.nodes,
+                &mut c.applier,
+                args,
+                &factory,
+                &update,
+            ) {
+                c.report.updated.push(current_node_key);
+            }
+            if is_dirty {
+                c.dirty_nodes.remove(&current_node_key);
+            }
+            c.end_layout_node(parent_node_key);
+            current_node_key
+        };
+        let current_node_key = composable();
+        // keep the latest layout for when the node recomposes on its own
+        let mut c = parent_scope.composer.write();
+        c.composables.insert(current_node_key, Box::new(composable));
+    }
+}
+
+impl<S, N> Recomposer<S, N>
+where
+    S: MaybeSendSync + 'static,
+    N: ComposeNode,
+{
+    /// Measures the outermost layout nodes with `constraints` and places them at the origin,
+    /// composing the children their layouts request. Runs after a completed recomposition.
+    pub fn layout(&mut self, constraints: Constraints) -> RecomposeReport {
+        let start = Instant::now();
+        let roots = {
+            let mut c = self.composer.write();
+            assert!(
+                !c.recomposing,
+                "layout pass while a recomposition is in progress"
+            );
+            for entry in c.layouts.values_mut() {
+                entry.placement = None;
+            }
+            c.report = RecomposeReport::default();
+            layout_nodes(&c, c.root_node_key)
+        };
+        for node_key in roots {
+            let size = measure_node(self.composer, node_key, constraints);
+            let mut c = self.composer.write();
+            if let Some(entry) = c.layouts.get_mut(&node_key) {
+                entry.placement = Some(Placement {
+                    offset: Point::ZERO,
+                    size,
+                });
+            }
+        }
+        let mut c = self.composer.write();
+        c.unmount_stale_nodes();
+        c.end_apply();
+        let mut report = mem::take(&mut c.report);
+        drop(c);
+        self.apply_effects();
+        report.duration += start.elapsed();
+        report
+    }
+
+    /// `None` for nodes the last layout pass did not place.
+    #[inline(always)]
+    pub fn placement(&self, node_key: NodeKey) -> Option<Placement> {
+        let c = self.composer.read();
+        c.layouts.get(&node_key).and_then(|entry| entry.placement)
+    }
+}
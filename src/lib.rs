@@ -3,19 +3,53 @@
 mod loc;
 pub use loc::Loc;
 
+mod animation;
+pub use animation::{AnimationSpec, Easing, Interpolate};
+
+mod applier;
+pub use applier::Applier;
+
+mod boundary;
+pub use boundary::{BoundaryError, ErrorBoundary};
+
 mod composer;
 pub use composer::{AnyData, Composable, ComposeNode, Composer, Node, NodeKey};
 
 mod subcompose;
 pub use subcompose::{
-    SlotId, SubcomposeHandle, SubcomposeRegistry, SubcomposeScope, Subcomposition,
+    PrecomposeHandle, SlotId, SlotRetention, SlotStatus, SubcomposeHandle, SubcomposeRegistry,
+    SubcomposeScope, Subcomposition,
 };
 
+mod derived;
+pub use derived::DerivedState;
+
+mod effect;
+
+mod frame;
+pub use frame::{FrameClock, ManualFrameClock, SystemFrameClock};
+
+mod key;
+
+#[cfg(feature = "layout")]
+pub mod layout;
+
+mod local;
+pub use local::CompositionLocal;
+
 mod recomposer;
-pub use recomposer::Recomposer;
+pub use recomposer::{RecomposeError, Recomposer};
+
+mod sender;
+pub use sender::StateSender;
 
 mod state;
-pub use state::{State, StateId};
+pub use state::{
+    MutationPolicy, NeverEqual, ReferentialEquality, State, StateError, StateId, StructuralEquality,
+};
+
+mod report;
+pub use report::RecomposeReport;
 
 mod scope;
 pub use scope::{Root, Scope, ScopeId};
@@ -23,3 +57,8 @@ pub use scope::{Root, Scope, ScopeId};
 pub mod utils;
 
 mod map;
+
+mod storage;
+pub use storage::MaybeSendSync;
+
+mod task;
@@ -0,0 +1,54 @@
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use crate::{Loc, StateId};
+
+pub struct CompositionLocal<T> {
+    pub(crate) id: Loc,
+    pub(crate) default: fn() -> T,
+}
+
+impl<T> CompositionLocal<T> {
+    #[track_caller]
+    #[inline(always)]
+    pub const fn new(default: fn() -> T) -> Self {
+        Self {
+            id: Loc::new(),
+            default,
+        }
+    }
+}
+
+impl<T> Clone for CompositionLocal<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CompositionLocal<T> {}
+
+impl<T> Debug for CompositionLocal<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "CompositionLocal({:?})", self.id)
+    }
+}
+
+// provided locals form a persistent list so every node can keep the one it was composed with
+pub(crate) struct LocalScope {
+    pub local: Loc,
+    pub state: StateId,
+    pub parent: Option<Arc<LocalScope>>,
+}
+
+impl LocalScope {
+    pub fn find(&self, local: Loc) -> Option<StateId> {
+        let mut scope = Some(self);
+        while let Some(s) = scope {
+            if s.local == local {
+                return Some(s.state);
+            }
+            scope = s.parent.as_deref();
+        }
+        None
+    }
+}
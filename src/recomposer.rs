@@ -1,87 +1,287 @@
-use std::fmt::{Debug, Formatter};
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::mem;
 use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
 
 use generational_box::{GenerationalBox, Owner};
 
-use crate::{utils, ComposeNode, Composer, NodeKey, State};
+use crate::effect::EffectSlot;
+use crate::storage::{MaybeSendSync, Storage};
+use crate::{
+    derived, utils, Applier, Composable, ComposeNode, Composer, FrameClock, NodeKey,
+    RecomposeReport, State, StateId,
+};
 
 pub struct Recomposer<S, N>
 where
     N: ComposeNode,
 {
     #[allow(dead_code)]
-    pub(crate) owner: Owner,
-    pub(crate) composer: GenerationalBox<Composer<N>>,
+    pub(crate) owner: Owner<Storage>,
+    pub(crate) composer: GenerationalBox<Composer<N>, Storage>,
     pub(crate) root_state: State<S, N>,
 }
 
 impl<S, N> Recomposer<S, N>
 where
-    S: 'static,
+    S: MaybeSendSync + 'static,
     N: ComposeNode,
 {
-    pub fn recompose(&mut self) {
+    pub fn recompose(&mut self) -> RecomposeReport {
+        match self.run_pass(None) {
+            Poll::Ready(report) => report,
+            Poll::Pending => unreachable!("a pass without deadline always completes"),
+        }
+    }
+
+    /// Recomposes dirty nodes until `deadline`, pausing between nodes. A paused pass resumes
+    /// on the next call (or `recompose`), stale nodes are only unmounted once it completes.
+    #[inline(always)]
+    pub fn recompose_with_budget(&mut self, deadline: Instant) -> Poll<RecomposeReport> {
+        self.run_pass(Some(deadline))
+    }
+
+    #[inline(always)]
+    pub fn is_recomposing(&self) -> bool {
+        self.composer.read().recomposing
+    }
+
+    fn run_pass(&mut self, deadline: Option<Instant>) -> Poll<RecomposeReport> {
+        let start = Instant::now();
+        if !self.composer.read().recomposing {
+            self.begin_pass();
+        }
+        // recomposing a node may invalidate others (e.g. a changed composition local),
+        // so keep going until no dirty node is left
+        while let Some((node_key, composable)) = self.next_dirty_composable() {
+            self.compose_dirty(node_key, composable);
+            let mut c = self.composer.write();
+            c.end_dirty_node(node_key);
+            if !c.subcompositions.is_empty() {
+                c.touch_slots(node_key);
+            }
+            // dirty nodes the pass detached are no work left
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                && c.next_dirty_node().is_some()
+            {
+                c.report.duration += start.elapsed();
+                return Poll::Pending;
+            }
+        }
+        let mut c = self.composer.write();
+        c.unmount_stale_nodes();
+        c.end_apply();
+        c.recomposing = false;
+        let mut report = mem::take(&mut c.report);
+        drop(c);
+        self.apply_effects();
+        report.duration += start.elapsed();
+        Poll::Ready(report)
+    }
+
+    fn compose_dirty(&mut self, node_key: NodeKey, composable: Box<dyn Composable>) {
+        let boundary = self.composer.read().enclosing_boundary(node_key);
+        let Some(boundary) = boundary else {
+            composable.compose();
+            return;
+        };
+        // the boundary is not on the stack when a node inside it recomposes on its own,
+        // so drop the subtree here and let the boundary compose it again
+        let snapshot = self.composer.read().boundary_snapshot();
+        if panic::catch_unwind(AssertUnwindSafe(|| composable.compose())).is_err() {
+            let mut c = self.composer.write();
+            c.rollback_boundary(boundary, snapshot);
+            c.dirty_nodes.insert(boundary);
+            // the rollback changed the tree around the queued nodes
+            c.dirty_order.clear();
+        }
+    }
+
+    fn begin_pass(&mut self) {
         let mut c = self.composer.write();
+        c.recomposing = true;
+        c.report = RecomposeReport::default();
+        c.apply_inbox();
         c.dirty_nodes.clear();
-        for state_id in c.dirty_states.drain().collect::<Vec<_>>() {
-            if let Some(nodes) = c.used_by.get(&state_id).cloned() {
+        c.dirty_order.clear();
+        // nodes dirtied while detached recompose if they were attached since
+        let detached_dirty = mem::take(&mut c.detached_dirty);
+        c.dirty_nodes.extend(detached_dirty);
+        let mut dirty_states = c.dirty_states.drain().collect::<Vec<_>>();
+        dirty_states.sort();
+        for state_id in &dirty_states {
+            if let Some(nodes) = c.used_by.get(state_id).cloned() {
                 c.dirty_nodes.extend(nodes);
             }
         }
-        let mut composables = Vec::with_capacity(c.dirty_nodes.len());
-        for node_key in &c.dirty_nodes {
-            if let Some(composable) = c.composables.get(node_key).cloned() {
-                composables.push((*node_key, composable));
-            }
+        if mem::take(&mut c.frame_advanced) {
+            let waiters = mem::take(&mut c.frame_waiters);
+            c.dirty_nodes.extend(waiters);
         }
         drop(c);
-        for (node_key, composable) in composables {
-            {
-                let mut c = self.composer.write();
-                c.current_node_key = node_key;
+        derived::invalidate(self.composer, &dirty_states);
+        self.composer.write().report.dirty_states = dirty_states;
+    }
+
+    /// Recomposes until no state is left dirty, returning the number of passes run.
+    /// At least one pass always runs, so `max_iterations` must not be 0.
+    pub fn recompose_until_stable(
+        &mut self,
+        max_iterations: usize,
+    ) -> Result<usize, RecomposeError> {
+        assert!(
+            max_iterations > 0,
+            "recompose_until_stable needs at least one pass"
+        );
+        for iteration in 1..=max_iterations {
+            self.recompose();
+            if self.composer.read().dirty_states.is_empty() {
+                return Ok(iteration);
             }
-            composable.compose();
         }
+        let mut states = self
+            .composer
+            .read()
+            .dirty_states
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        states.sort();
+        Err(RecomposeError::Unstable {
+            iterations: max_iterations,
+            states,
+        })
+    }
+
+    fn next_dirty_composable(&mut self) -> Option<(NodeKey, Box<dyn Composable>)> {
         let mut c = self.composer.write();
         let c = c.deref_mut();
-        let unmount_nodes = c
-            .unmount_nodes
-            .difference(&c.mount_nodes)
-            .cloned()
-            .collect::<Vec<_>>();
-        for n in unmount_nodes {
-            c.subcompositions.remove(&n);
-            for entry in c.subcompositions.values_mut() {
-                for slot in entry.slots.values_mut() {
-                    if slot.node_key == Some(n) {
-                        slot.node_key = None;
-                    }
-                }
+        // parents go first, their re-execution covers dirty descendants, and nodes
+        // detached earlier in this pass are dropped
+        while let Some(node_key) = c.next_dirty_node() {
+            if let Some(composable) = c.composables.get(&node_key).cloned() {
+                c.current_node_key = node_key;
+                c.current_locals = c.locals.get(&node_key).cloned();
+                return Some((node_key, composable));
             }
-            c.composables.remove(&n);
-            c.nodes.remove(n);
-            if let Some(node_states) = c.states.remove(&n) {
-                for state in node_states.keys() {
-                    c.used_by.remove(state);
+            c.dirty_nodes.remove(&node_key);
+        }
+        c.current_locals = None;
+        None
+    }
+
+    pub(crate) fn apply_effects(&mut self) {
+        let (disposals, effects) = {
+            let mut c = self.composer.write();
+            let disposals = mem::take(&mut c.pending_disposals);
+            let effects = mem::take(&mut c.pending_effects);
+            (disposals, effects)
+        };
+        for dispose in disposals {
+            dispose();
+        }
+        for pending in effects {
+            let prev_dispose = {
+                let mut c = self.composer.write();
+                if !c.nodes.contains(pending.node_key) {
+                    continue;
                 }
+                c.effects
+                    .get_mut(&pending.node_key)
+                    .and_then(|effects| effects.get_mut(&pending.loc))
+                    .and_then(|slot| slot.dispose.take())
+            };
+            if let Some(dispose) = prev_dispose {
+                dispose();
+            }
+            let dispose = (pending.effect)();
+            if let Some(keys) = pending.keys {
+                let mut c = self.composer.write();
+                let effects = c.effects.entry(pending.node_key).or_default();
+                effects.insert(pending.loc, EffectSlot { keys, dispose });
             }
-            let use_states = c.uses.remove(&n);
-            if let Some(use_states) = use_states {
-                for state in use_states {
-                    if let Some(used_by) = c.used_by.get_mut(&state) {
-                        used_by.remove(&n);
+        }
+    }
+
+    pub fn poll_tasks(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        // futures are polled outside the composer lock so they can read and set states
+        let running = {
+            let mut c = self.composer.write();
+            let mut running = Vec::new();
+            for (node_key, tasks) in c.tasks.iter_mut() {
+                for (loc, slot) in tasks.iter_mut() {
+                    if let Some(future) = slot.future.take() {
+                        running.push((*node_key, *loc, future));
                     }
                 }
             }
+            running
+        };
+        let mut pending = false;
+        for (node_key, loc, mut future) in running {
+            if future.as_mut().poll(cx).is_ready() {
+                continue;
+            }
+            pending = true;
+            let mut c = self.composer.write();
+            if let Some(slot) = c.tasks.get_mut(&node_key).and_then(|t| t.get_mut(&loc)) {
+                slot.future = Some(future);
+            }
         }
-        c.mount_nodes.clear();
-        c.unmount_nodes.clear();
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+
+    pub fn advance_frame(&mut self, frame_nanos: u64) -> RecomposeReport {
+        let mut c = self.composer.write();
+        // frame time never goes backwards
+        c.frame_nanos = c.frame_nanos.max(frame_nanos);
+        c.frame_advanced = true;
+        c.step_animations();
+        drop(c);
+        self.recompose()
+    }
+
+    #[inline(always)]
+    pub fn next_frame(&mut self) -> RecomposeReport {
+        let frame_nanos = self.composer.read().frame_clock.frame_nanos();
+        self.advance_frame(frame_nanos)
+    }
+
+    #[inline(always)]
+    pub fn frame_nanos(&self) -> u64 {
+        self.composer.read().frame_nanos
+    }
+
+    #[inline(always)]
+    pub fn is_awaiting_frame(&self) -> bool {
+        !self.composer.read().frame_waiters.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn is_animating(&self) -> bool {
+        let c = self.composer.read();
+        c.animations.values().any(|a| a.is_running())
+    }
+
+    pub fn set_frame_clock<C>(&mut self, clock: C)
+    where
+        C: FrameClock + 'static,
+    {
+        self.composer.write().frame_clock = Box::new(clock);
     }
 
     #[inline(always)]
-    pub fn recompose_with(&mut self, new_state: S) {
+    pub fn recompose_with(&mut self, new_state: S) -> RecomposeReport {
         self.root_state.set(new_state);
-        self.recompose();
+        self.recompose()
     }
 
     #[inline(always)]
@@ -89,6 +289,20 @@ where
         self.composer.read().root_node_key
     }
 
+    pub fn set_applier<A>(&mut self, applier: A)
+    where
+        A: Applier<N> + 'static,
+    {
+        self.composer.write().set_applier(Box::new(applier));
+    }
+
+    pub fn set_waker<W>(&mut self, waker: W)
+    where
+        W: Fn() + Send + Sync + 'static,
+    {
+        self.composer.read().inbox.set_waker(Arc::new(waker));
+    }
+
     #[inline(always)]
     pub fn with_context<F, T>(&self, func: F) -> T
     where
@@ -172,11 +386,41 @@ where
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecomposeError {
+    /// States were still written during composition after the last pass.
+    Unstable {
+        iterations: usize,
+        states: Vec<StateId>,
+    },
+}
+
+impl Display for RecomposeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RecomposeError::Unstable { iterations, states } => {
+                write!(
+                    f,
+                    "composition not stable after {} passes, dirty states created at",
+                    iterations
+                )?;
+                for (idx, state) in states.iter().enumerate() {
+                    let sep = if idx == 0 { " " } else { ", " };
+                    write!(f, "{}{:?}", sep, state.loc())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for RecomposeError {}
+
 impl<S, N> Debug for Recomposer<S, N>
 where
     N: ComposeNode + Debug,
 {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let c = self.composer.read();
         f.debug_struct("Recomposer")
             .field("nodes", &c.nodes)
@@ -187,3 +431,22 @@ where
             .finish()
     }
 }
+
+impl<S, N> Drop for Recomposer<S, N>
+where
+    N: ComposeNode,
+{
+    fn drop(&mut self) {
+        let disposals = {
+            let mut c = self.composer.write();
+            let mut disposals = mem::take(&mut c.pending_disposals);
+            for (_, effects) in c.effects.drain() {
+                disposals.extend(effects.into_values().filter_map(|e| e.dispose));
+            }
+            disposals
+        };
+        for dispose in disposals {
+            dispose();
+        }
+    }
+}
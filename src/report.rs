@@ -0,0 +1,22 @@
+use std::time::Duration;
+
+use crate::{NodeKey, StateId};
+
+/// What a single recomposition pass did, node keys are in visiting order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecomposeReport {
+    pub dirty_states: Vec<StateId>,
+    pub recomposed: Vec<NodeKey>,
+    pub skipped: Vec<NodeKey>,
+    pub mounted: Vec<NodeKey>,
+    pub unmounted: Vec<NodeKey>,
+    pub updated: Vec<NodeKey>,
+    pub duration: Duration,
+}
+
+impl RecomposeReport {
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.recomposed.is_empty() && self.unmounted.is_empty()
+    }
+}
@@ -1,22 +1,38 @@
+use std::any::TypeId;
 use std::fmt::{self, Debug, Formatter};
+use std::future::Future;
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::ops::DerefMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 
 use generational_box::GenerationalBox;
 use slab::Slab;
 
+use crate::animation::{Animation, AnimationSpec, Interpolate};
+use crate::applier::ApplierQueue;
+use crate::boundary::{BoundaryError, ErrorBoundary};
 use crate::composer::NodeKey;
+use crate::derived::{eq_any, DerivedEntry};
+use crate::effect::{Dispose, PendingEffect};
+use crate::local::LocalScope;
+use crate::state::any_policy;
+use crate::storage::{MaybeSendSync, Storage};
 use crate::subcompose::{SubcomposeRegistry, Subcomposition};
-use crate::{AnyData, ComposeNode, Composer, Loc, Node, State, StateId};
+use crate::task::TaskSlot;
+use crate::{
+    AnyData, ComposeNode, Composer, CompositionLocal, DerivedState, Loc, MutationPolicy, Node,
+    State, StateId,
+};
 
 pub struct Scope<S, N>
 where
     N: ComposeNode,
 {
     pub id: ScopeId,
-    pub(crate) composer: GenerationalBox<Composer<N>>,
-    ty: PhantomData<S>,
+    pub(crate) composer: GenerationalBox<Composer<N>, Storage>,
+    ty: PhantomData<fn() -> S>,
 }
 
 impl<S, N> Clone for Scope<S, N>
@@ -36,7 +52,7 @@ where
     N: ComposeNode,
 {
     #[inline(always)]
-    pub(crate) fn new(id: ScopeId, composer: GenerationalBox<Composer<N>>) -> Self {
+    pub(crate) fn new(id: ScopeId, composer: GenerationalBox<Composer<N>, Storage>) -> Self {
         Self {
             id,
             composer,
@@ -62,8 +78,8 @@ where
     #[track_caller]
     pub fn use_state<F, T>(&self, init: F) -> State<T, N>
     where
-        T: 'static,
-        F: Fn() -> T + 'static,
+        T: MaybeSendSync + 'static,
+        F: Fn() -> T + MaybeSendSync + 'static,
     {
         let mut c = self.composer.write();
         let c = c.deref_mut();
@@ -74,13 +90,261 @@ where
         State::new(id, self.composer)
     }
 
+    #[track_caller]
+    pub fn use_state_with_policy<F, T, P>(&self, init: F, policy: P) -> State<T, N>
+    where
+        T: MaybeSendSync + 'static,
+        F: Fn() -> T + MaybeSendSync + 'static,
+        P: MutationPolicy<T>,
+    {
+        let mut c = self.composer.write();
+        let c = c.deref_mut();
+        let current_node_key = c.current_node_key;
+        let id = StateId::new(current_node_key);
+        let scope_states = c.states.entry(current_node_key).or_default();
+        scope_states.entry(id).or_insert_with(|| {
+            c.state_policies.insert(id, any_policy(policy));
+            Box::new(init())
+        });
+        State::new(id, self.composer)
+    }
+
+    #[track_caller]
+    pub fn derived_state_of<F, T>(&self, compute: F) -> DerivedState<T, N>
+    where
+        T: PartialEq + MaybeSendSync + 'static,
+        F: Fn() -> T + MaybeSendSync + 'static,
+    {
+        let mut c = self.composer.write();
+        let id = StateId::new(c.current_node_key);
+        let node_derived = c.derived.entry(id.node_key).or_default();
+        node_derived.entry(id).or_insert_with(|| DerivedEntry {
+            compute: Arc::new(move || Box::new(compute())),
+            eq: eq_any::<T>,
+            stale: true,
+            changed: false,
+        });
+        DerivedState::new(id, self.composer)
+    }
+
+    #[track_caller]
+    pub fn provide<T, C>(&self, local: CompositionLocal<T>, value: T, content: C)
+    where
+        T: PartialEq + MaybeSendSync + 'static,
+        C: FnOnce(Self),
+    {
+        let parent_locals = {
+            let mut c = self.composer.write();
+            let c = c.deref_mut();
+            let current_node_key = c.current_node_key;
+            let id = StateId::new(current_node_key);
+            let scope_states = c.states.entry(current_node_key).or_default();
+            match scope_states.get_mut(&id) {
+                Some(prev) if prev.downcast_ref::<T>() != Some(&value) => {
+                    *prev = Box::new(value);
+                    // readers are recomposed within the same pass
+                    if let Some(readers) = c.used_by.get(&id) {
+                        c.dirty_nodes.extend(readers);
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    scope_states.insert(id, Box::new(value));
+                }
+            }
+            let parent_locals = c.current_locals.clone();
+            c.current_locals = Some(Arc::new(LocalScope {
+                local: local.id,
+                state: id,
+                parent: parent_locals.clone(),
+            }));
+            parent_locals
+        };
+        content(*self);
+        self.composer.write().current_locals = parent_locals;
+    }
+
+    pub fn current<T>(&self, local: CompositionLocal<T>) -> T
+    where
+        T: Clone + MaybeSendSync + 'static,
+    {
+        let state_id = {
+            let c = self.composer.read();
+            c.current_locals.as_ref().and_then(|l| l.find(local.id))
+        };
+        match state_id {
+            Some(id) => State::<T, N>::new(id, self.composer).get(),
+            None => (local.default)(),
+        }
+    }
+
+    #[track_caller]
+    pub fn use_disposable_effect<K, F, D>(&self, keys: K, effect: F)
+    where
+        K: PartialEq + MaybeSendSync + 'static,
+        F: FnOnce() -> D + MaybeSendSync + 'static,
+        D: FnOnce() + MaybeSendSync + 'static,
+    {
+        let loc = Loc::new();
+        let mut c = self.composer.write();
+        let c = c.deref_mut();
+        let node_key = c.current_node_key;
+        let unchanged = c
+            .effects
+            .get(&node_key)
+            .and_then(|effects| effects.get(&loc))
+            .and_then(|slot| slot.keys.downcast_ref::<K>())
+            .is_some_and(|prev| *prev == keys);
+        c.pending_effects.retain(|e| !e.is_same_slot(node_key, loc));
+        if !unchanged {
+            c.pending_effects.push(PendingEffect {
+                node_key,
+                loc,
+                keys: Some(Box::new(keys)),
+                effect: Box::new(move || Some(Box::new(effect()) as Dispose)),
+            });
+        }
+    }
+
+    #[track_caller]
+    #[inline(always)]
+    pub fn use_effect<K, F>(&self, keys: K, effect: F)
+    where
+        K: PartialEq + MaybeSendSync + 'static,
+        F: FnOnce() + MaybeSendSync + 'static,
+    {
+        self.use_disposable_effect(keys, move || {
+            effect();
+            || {}
+        });
+    }
+
+    #[track_caller]
+    pub fn use_task<K, F, Fut>(&self, keys: K, task: F)
+    where
+        K: PartialEq + MaybeSendSync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()> + MaybeSendSync + 'static,
+    {
+        let loc = Loc::new();
+        let (node_key, unchanged) = {
+            let c = self.composer.read();
+            let node_key = c.current_node_key;
+            let unchanged = c
+                .tasks
+                .get(&node_key)
+                .and_then(|tasks| tasks.get(&loc))
+                .and_then(|slot| slot.keys.downcast_ref::<K>())
+                .is_some_and(|prev| *prev == keys);
+            (node_key, unchanged)
+        };
+        if !unchanged {
+            let future = Box::pin(task());
+            // replacing the slot drops, and so cancels, the previous future
+            let mut c = self.composer.write();
+            c.tasks.entry(node_key).or_default().insert(
+                loc,
+                TaskSlot {
+                    keys: Box::new(keys),
+                    future: Some(future),
+                },
+            );
+        }
+    }
+
     #[track_caller]
     #[inline(always)]
-    pub fn key<C>(&self, key: usize, content: C)
+    pub fn launch<F, Fut>(&self, task: F)
     where
-        C: Fn(Self) + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = ()> + MaybeSendSync + 'static,
     {
-        self.composer.write().key_stack.push(key);
+        self.use_task((), task);
+    }
+
+    #[track_caller]
+    pub fn animate_as_state<T>(&self, target: T, spec: AnimationSpec<T>) -> State<T, N>
+    where
+        T: Interpolate,
+    {
+        let initial = target.clone();
+        let state = self.use_state(move || initial.clone());
+        let mut c = self.composer.write();
+        let c = c.deref_mut();
+        let frame_nanos = c.frame_nanos;
+        match c.animations.get_mut(&state.id) {
+            Some(entry) => {
+                let animation = entry.as_any_mut().downcast_mut::<Animation<T>>().unwrap();
+                if animation.to != target {
+                    // retarget from wherever the value is now
+                    let current = c.states[&state.id.node_key][&state.id]
+                        .downcast_ref::<T>()
+                        .unwrap()
+                        .clone();
+                    animation.restart(current, target, spec, frame_nanos);
+                }
+            }
+            None => {
+                c.animations
+                    .insert(state.id, Box::new(Animation::idle(target, spec)));
+            }
+        }
+        state
+    }
+
+    /// Reads the current frame time and recomposes this node on the next `advance_frame`.
+    pub fn with_frame_nanos<F, R>(&self, func: F) -> R
+    where
+        F: FnOnce(u64) -> R,
+    {
+        let frame_nanos = {
+            let mut c = self.composer.write();
+            let node_key = c.current_node_key;
+            c.frame_waiters.insert(node_key);
+            c.frame_nanos
+        };
+        func(frame_nanos)
+    }
+
+    #[track_caller]
+    pub fn use_side_effect<F>(&self, effect: F)
+    where
+        F: FnOnce() + MaybeSendSync + 'static,
+    {
+        let loc = Loc::new();
+        let mut c = self.composer.write();
+        let node_key = c.current_node_key;
+        c.pending_effects.push(PendingEffect {
+            node_key,
+            loc,
+            keys: None,
+            effect: Box::new(move || {
+                effect();
+                None
+            }),
+        });
+    }
+
+    #[track_caller]
+    #[inline(always)]
+    pub fn key<K, C>(&self, key: K, content: C)
+    where
+        K: Hash + Eq + MaybeSendSync + 'static,
+        C: Fn(Self) + MaybeSendSync + 'static,
+    {
+        {
+            let mut c = self.composer.write();
+            let key = c.keys.intern(key);
+            let node_key = c.current_node_key;
+            let siblings = c.keyed_siblings.entry(node_key).or_default();
+            assert!(
+                siblings.insert(key),
+                "duplicate key for sibling composed at {:?} under node {}",
+                Loc::new(),
+                node_key
+            );
+            c.key_stack.push((key, node_key));
+        }
         content(*self);
         self.composer.write().key_stack.pop();
     }
@@ -88,7 +352,7 @@ where
     #[track_caller]
     pub fn subcompose<C>(&self, content: C) -> Subcomposition<N>
     where
-        C: Fn(SubcomposeRegistry<'_, N>) + Clone + 'static,
+        C: Fn(SubcomposeRegistry<'_, N>) + Clone + MaybeSendSync + 'static,
     {
         let node_key = {
             let c = self.composer.read();
@@ -108,18 +372,18 @@ where
         update: U,
     ) where
         T: 'static,
-        C: Fn(Scope<T, N>) + Clone + 'static,
-        I: Fn() -> A + Clone + 'static,
-        A: 'static,
-        F: Fn(A, &mut N::Context) -> N + Clone + 'static,
-        U: Fn(&mut N, A, &mut N::Context) + Clone + 'static,
+        C: Fn(Scope<T, N>) + Clone + MaybeSendSync + 'static,
+        I: Fn() -> A + Clone + MaybeSendSync + 'static,
+        A: MaybeSendSync + 'static,
+        F: Fn(A, &mut N::Context) -> N + Clone + MaybeSendSync + 'static,
+        U: Fn(&mut N, A, &mut N::Context) + Clone + MaybeSendSync + 'static,
     {
         let parent_scope = *self;
         let composable = move || {
             let mut current_scope = child_scope;
             let (parent_node_key, current_node_key, is_dirty) = {
                 let mut c = parent_scope.composer.write();
-                if let Some(key) = c.key_stack.last().copied() {
+                if let Some(key) = c.child_key() {
                     current_scope.set_key(key);
                 }
                 let parent_node_key = c.current_node_key;
@@ -135,14 +399,17 @@ where
                 let args = input();
                 let mut c = parent_scope.composer.write();
                 let c = c.deref_mut();
-                update_node(
+                if update_node(
                     current_node_key,
                     &mut c.context,
                     &mut c.nodes,
+                    &mut c.applier,
                     args,
                     &factory,
                     &update,
-                );
+                ) {
+                    c.report.updated.push(current_node_key);
+                }
                 (parent_node_key, current_node_key, is_dirty)
             };
             content(current_scope);
@@ -161,6 +428,82 @@ where
             .or_insert_with(|| Box::new(composable));
     }
 
+    // like `create_node`, but the node is re-run whenever its parent is, and skipped
+    // together with its content when the args are equal to the previous ones;
+    // `A: Clone` because a copy is kept for the next comparison while the factory and
+    // update take the args by value, the same as in `create_node`
+    pub fn create_memo_node<C, T, I, A, F, U>(
+        &self,
+        child_scope: Scope<T, N>,
+        content: C,
+        input: I,
+        factory: F,
+        update: U,
+    ) where
+        T: 'static,
+        C: Fn(Scope<T, N>) + Clone + MaybeSendSync + 'static,
+        I: Fn() -> A + Clone + MaybeSendSync + 'static,
+        A: PartialEq + Clone + MaybeSendSync + 'static,
+        F: Fn(A, &mut N::Context) -> N + Clone + MaybeSendSync + 'static,
+        U: Fn(&mut N, A, &mut N::Context) + Clone + MaybeSendSync + 'static,
+    {
+        let parent_scope = *self;
+        let composable = move || {
+            let mut current_scope = child_scope;
+            let (parent_node_key, current_node_key, is_dirty) = {
+                let mut c = parent_scope.composer.write();
+                if let Some(key) = c.child_key() {
+                    current_scope.set_key(key);
+                }
+                let parent_node_key = c.current_node_key;
+                c.start_node(parent_node_key, current_scope.id);
+                let current_node_key = c.current_node_key;
+                let is_visited = c.composables.contains_key(&current_node_key);
+                let is_dirty = c.dirty_nodes.contains(&current_node_key);
+                drop(c);
+                let args = input();
+                let mut c = parent_scope.composer.write();
+                let c = c.deref_mut();
+                let is_changed = c
+                    .node_args
+                    .get(&current_node_key)
+                    .and_then(|prev| prev.downcast_ref::<A>())
+                    .is_none_or(|prev| *prev != args);
+                if !is_changed && !is_dirty && is_visited {
+                    c.skip_node(parent_node_key);
+                    return current_node_key;
+                }
+                if is_changed {
+                    c.node_args.insert(current_node_key, Box::new(args.clone()));
+                    if update_node(
+                        current_node_key,
+                        &mut c.context,
+                        &mut c.nodes,
+                        &mut c.applier,
+                        args,
+                        &factory,
+                        &update,
+                    ) {
+                        c.report.updated.push(current_node_key);
+                    }
+                }
+                (parent_node_key, current_node_key, is_dirty)
+            };
+            content(current_scope);
+            let mut c = parent_scope.composer.write();
+            let c = c.deref_mut();
+            if is_dirty {
+                c.dirty_nodes.remove(&current_node_key);
+            }
+            c.end_node(parent_node_key);
+            current_node_key
+        };
+        let current_node_key = composable();
+        // keep the latest closures, they capture what the parent passed this time
+        let mut c = parent_scope.composer.write();
+        c.composables.insert(current_node_key, Box::new(composable));
+    }
+
     #[inline(always)]
     pub fn create_any_node<C, T, I, A, E, F, U>(
         &self,
@@ -171,13 +514,13 @@ where
         update: U,
     ) where
         T: 'static,
-        C: Fn(Scope<T, N>) + Clone + 'static,
-        I: Fn() -> A + Clone + 'static,
-        A: 'static,
+        C: Fn(Scope<T, N>) + Clone + MaybeSendSync + 'static,
+        I: Fn() -> A + Clone + MaybeSendSync + 'static,
+        A: MaybeSendSync + 'static,
         N: AnyData<E>,
-        E: 'static,
-        F: Fn(A, &mut N::Context) -> E + Clone + 'static,
-        U: Fn(&mut E, A, &mut N::Context) + Clone + 'static,
+        E: MaybeSendSync + 'static,
+        F: Fn(A, &mut N::Context) -> E + Clone + MaybeSendSync + 'static,
+        U: Fn(&mut E, A, &mut N::Context) + Clone + MaybeSendSync + 'static,
     {
         self.create_node(
             child_scope,
@@ -193,31 +536,96 @@ where
             },
         );
     }
+
+    // a node without data, a panic in `content` discards the subtree it built and composes
+    // `fallback` in its place
+    #[track_caller]
+    pub fn error_boundary<C, F>(&self, content: C, fallback: F)
+    where
+        C: Fn(Scope<ErrorBoundary, N>) + Clone + MaybeSendSync + 'static,
+        F: Fn(Scope<ErrorBoundary, N>, BoundaryError<N>) + Clone + MaybeSendSync + 'static,
+    {
+        let child_scope = self.child::<ErrorBoundary>();
+        let parent_scope = *self;
+        let composable = move || {
+            let mut current_scope = child_scope;
+            let (parent_node_key, current_node_key, is_dirty) = {
+                let mut c = parent_scope.composer.write();
+                if let Some(key) = c.child_key() {
+                    current_scope.set_key(key);
+                }
+                let parent_node_key = c.current_node_key;
+                c.start_node(parent_node_key, current_scope.id);
+                let current_node_key = c.current_node_key;
+                let is_visited = c.composables.contains_key(&current_node_key);
+                let is_dirty = c.dirty_nodes.contains(&current_node_key);
+                if !is_dirty && is_visited {
+                    c.skip_node(parent_node_key);
+                    return current_node_key;
+                }
+                c.boundaries.insert(current_node_key);
+                (parent_node_key, current_node_key, is_dirty)
+            };
+            let attempt = current_scope.use_state(|| 0);
+            attempt.with(|_| {});
+            let snapshot = parent_scope.composer.read().boundary_snapshot();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| content(current_scope)));
+            if let Err(payload) = result {
+                let error = BoundaryError::new(payload.as_ref(), attempt);
+                parent_scope
+                    .composer
+                    .write()
+                    .rollback_boundary(current_node_key, snapshot);
+                // keyed so fallback nodes never match the content's by position
+                let fallback = fallback.clone();
+                current_scope.key(TypeId::of::<ErrorBoundary>(), move |scope| {
+                    fallback(scope, error.clone())
+                });
+            }
+            let mut c = parent_scope.composer.write();
+            let c = c.deref_mut();
+            if is_dirty {
+                c.dirty_nodes.remove(&current_node_key);
+            }
+            c.end_node(parent_node_key);
+            current_node_key
+        };
+        let current_node_key = composable();
+        let mut c = parent_scope.composer.write();
+        c.composables
+            .entry(current_node_key)
+            .or_insert_with(|| Box::new(composable));
+    }
 }
 
 // workaround of borrowing both context and nodes from Composer
 // https://smallcultfollowing.com/babysteps/blog/2018/11/01/after-nll-interprocedural-conflicts/
+// returns true when existing node data was updated rather than created
 #[inline(always)]
-fn update_node<N, A, F, U>(
+pub(crate) fn update_node<N, A, F, U>(
     node_key: NodeKey,
     context: &mut N::Context,
     nodes: &mut Slab<Node<N>>,
+    applier: &mut ApplierQueue<N>,
     args: A,
     factory: &F,
     update: &U,
-) where
+) -> bool
+where
     N: ComposeNode,
-    A: 'static,
-    F: Fn(A, &mut N::Context) -> N + Clone + 'static,
-    U: Fn(&mut N, A, &mut N::Context) + Clone + 'static,
+    A: MaybeSendSync + 'static,
+    F: Fn(A, &mut N::Context) -> N + Clone + MaybeSendSync + 'static,
+    U: Fn(&mut N, A, &mut N::Context) + Clone + MaybeSendSync + 'static,
 {
     let node = nodes.get_mut(node_key).unwrap();
+    let is_update = node.data.is_some();
     if let Some(data) = node.data.as_mut() {
         update(data, args, context);
     } else {
-        let data = factory(args, context);
-        node.data = Some(data);
+        node.data = Some(factory(args, context));
     }
+    applier.update(node_key);
+    is_update
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
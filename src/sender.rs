@@ -0,0 +1,98 @@
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::storage::{AnySendBox, MaybeSendSync};
+use crate::{StateError, StateId};
+
+type Waker = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct InboxInner {
+    updates: Vec<(StateId, AnySendBox)>,
+    waker: Option<Waker>,
+}
+
+// updates pushed from any thread, applied by the composer at the start of `recompose`
+#[derive(Default)]
+pub(crate) struct Inbox {
+    inner: Mutex<InboxInner>,
+}
+
+impl Inbox {
+    pub(crate) fn push(&self, state_id: StateId, value: AnySendBox) {
+        let waker = {
+            let mut inner = self.inner.lock().unwrap();
+            let was_empty = inner.updates.is_empty();
+            inner.updates.push((state_id, value));
+            if was_empty {
+                inner.waker.clone()
+            } else {
+                None
+            }
+        };
+        // fire outside the lock so the waker may send more updates
+        if let Some(waker) = waker {
+            waker();
+        }
+    }
+
+    pub(crate) fn take(&self) -> Vec<(StateId, AnySendBox)> {
+        mem::take(&mut self.inner.lock().unwrap().updates)
+    }
+
+    pub(crate) fn set_waker(&self, waker: Waker) {
+        self.inner.lock().unwrap().waker = Some(waker);
+    }
+}
+
+pub struct StateSender<T> {
+    id: StateId,
+    inbox: Weak<Inbox>,
+    ty: PhantomData<fn(T)>,
+}
+
+impl<T> StateSender<T>
+where
+    T: Send + MaybeSendSync + 'static,
+{
+    #[inline(always)]
+    pub(crate) fn new(id: StateId, inbox: &Arc<Inbox>) -> Self {
+        Self {
+            id,
+            inbox: Arc::downgrade(inbox),
+            ty: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> StateId {
+        self.id
+    }
+
+    pub fn send(&self, value: T) -> Result<(), StateError> {
+        let inbox = self
+            .inbox
+            .upgrade()
+            .ok_or(StateError::ComposerDropped(self.id))?;
+        inbox.push(self.id, Box::new(value));
+        Ok(())
+    }
+}
+
+impl<T> Clone for StateSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            inbox: self.inbox.clone(),
+            ty: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for StateSender<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateSender").field("id", &self.id).finish()
+    }
+}
@@ -1,9 +1,14 @@
-use std::fmt::{self, Debug, Formatter};
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
 use std::marker::PhantomData;
 use std::ops::DerefMut;
+use std::rc::Rc;
+use std::sync::Arc;
 
-use generational_box::GenerationalBox;
+use generational_box::{BorrowMutError, GenerationalBox};
 
+use crate::sender::StateSender;
+use crate::storage::{DynEq, MaybeSendSync, Storage};
 use crate::{ComposeNode, Composer, Loc, NodeKey};
 
 pub struct State<T, N>
@@ -11,17 +16,17 @@ where
     N: ComposeNode,
 {
     pub id: StateId,
-    composer: GenerationalBox<Composer<N>>,
+    composer: GenerationalBox<Composer<N>, Storage>,
     ty: PhantomData<T>,
 }
 
 impl<T, N> State<T, N>
 where
-    T: 'static,
+    T: MaybeSendSync + 'static,
     N: ComposeNode,
 {
     #[inline(always)]
-    pub(crate) fn new(id: StateId, composer: GenerationalBox<Composer<N>>) -> Self {
+    pub(crate) fn new(id: StateId, composer: GenerationalBox<Composer<N>, Storage>) -> Self {
         Self {
             id,
             composer,
@@ -33,98 +38,180 @@ where
     where
         F: Fn(&T) -> U,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let current_node_key = c.current_node_key;
-        let used_by = c.used_by.entry(self.id).or_default();
-        used_by.insert(current_node_key);
-        let uses = c.uses.entry(current_node_key).or_default();
-        uses.insert(self.id);
-        let scope_states = c.states.get(&self.id.node_key).unwrap();
-        let any_state = scope_states.get(&self.id).unwrap();
-        let state = any_state.downcast_ref::<T>().unwrap();
-        func(state)
+        expect(self.try_with(func))
     }
 
     pub fn with_untracked<F, U>(&self, func: F) -> U
     where
         F: Fn(&T) -> U,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let scope_states = c.states.get(&self.id.node_key).unwrap();
-        let any_state = scope_states.get(&self.id).unwrap();
-        let state = any_state.downcast_ref::<T>().unwrap();
-        func(state)
+        expect(self.try_access(false, |c| Ok(func(self.value(c)))))
     }
 
+    /// Always invalidates the state's readers, the mutation policy only applies to [`Self::set`]
+    /// since the value is changed in place and there is no previous one to compare against.
     pub fn with_mut<F, U>(&self, func: F) -> U
     where
         F: Fn(&mut T) -> U,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let current_node_key = c.current_node_key;
-        let used_by = c.used_by.entry(self.id).or_default();
-        used_by.insert(current_node_key);
-        let uses = c.uses.entry(current_node_key).or_default();
-        uses.insert(self.id);
-        let scope_states = c.states.get_mut(&self.id.node_key).unwrap();
-        let any_state = scope_states.get_mut(&self.id).unwrap();
-        let state = any_state.downcast_mut::<T>().unwrap();
-        c.dirty_states.insert(self.id);
-        func(state)
+        expect(self.try_access(true, |c| Ok(self.mutate(c, func))))
     }
 
+    /// Like [`Self::with_mut`], it invalidates the readers regardless of the mutation policy.
     pub fn with_mut_untracked<F, U>(&self, func: F) -> U
     where
         F: Fn(&mut T) -> U,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let scope_states = c.states.get_mut(&self.id.node_key).unwrap();
-        let any_state = scope_states.get_mut(&self.id).unwrap();
-        let state = any_state.downcast_mut::<T>().unwrap();
-        c.dirty_states.insert(self.id);
-        func(state)
+        expect(self.try_access(false, |c| Ok(self.mutate(c, func))))
     }
 
     pub fn get(&self) -> T
     where
         T: Clone,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let current_node_key = c.current_node_key;
-        let used_by = c.used_by.entry(self.id).or_default();
-        used_by.insert(current_node_key);
-        let uses = c.uses.entry(current_node_key).or_default();
-        uses.insert(self.id);
-        let scope_states = c.states.get(&self.id.node_key).unwrap();
-        let any_state = scope_states.get(&self.id).unwrap();
-        let state = any_state.downcast_ref::<T>().unwrap();
-        state.clone()
+        expect(self.try_get())
     }
 
     pub fn get_untracked(&self) -> T
     where
         T: Clone,
     {
-        let mut c = self.composer.write();
-        let c = c.deref_mut();
-        let scope_states = c.states.get(&self.id.node_key).unwrap();
-        let any_state = scope_states.get(&self.id).unwrap();
-        let state = any_state.downcast_ref::<T>().unwrap();
-        state.clone()
+        self.with_untracked(T::clone)
     }
 
     pub fn set(&self, value: T) {
-        let mut c = self.composer.write();
+        expect(self.try_set(value))
+    }
+
+    pub fn try_with<F, U>(&self, func: F) -> Result<U, StateError>
+    where
+        F: Fn(&T) -> U,
+    {
+        self.try_access(true, |c| Ok(func(self.value(c))))
+    }
+
+    pub fn try_get(&self) -> Result<T, StateError>
+    where
+        T: Clone,
+    {
+        self.try_with(T::clone)
+    }
+
+    pub fn try_set(&self, value: T) -> Result<(), StateError> {
+        self.try_access(false, |c| {
+            let scope_states = c.states.get_mut(&self.id.node_key).unwrap();
+            let val = scope_states.get_mut(&self.id).unwrap();
+            if let Some(equivalent) = c.state_policies.get(&self.id) {
+                if equivalent(val.as_ref(), &value) {
+                    return Ok(());
+                }
+            }
+            *val = Box::new(value);
+            c.dirty_states.insert(self.id);
+            Ok(())
+        })
+    }
+
+    pub fn sender(&self) -> StateSender<T>
+    where
+        T: Send,
+    {
+        expect(self.try_access(false, |c| Ok(StateSender::new(self.id, &c.inbox))))
+    }
+
+    fn try_access<F, U>(&self, track: bool, func: F) -> Result<U, StateError>
+    where
+        F: FnOnce(&mut Composer<N>) -> Result<U, StateError>,
+    {
+        let mut c = match self.composer.try_write() {
+            Ok(c) => c,
+            Err(BorrowMutError::Dropped(_)) => return Err(StateError::ComposerDropped(self.id)),
+            Err(err) => panic!("{}", err),
+        };
         let c = c.deref_mut();
+        let any_state = c
+            .states
+            .get(&self.id.node_key)
+            .and_then(|scope_states| scope_states.get(&self.id))
+            .ok_or(StateError::NodeDisposed(self.id))?;
+        if !any_state.is::<T>() {
+            return Err(StateError::TypeMismatch(self.id));
+        }
+        if track {
+            c.track_read(self.id);
+        }
+        func(c)
+    }
+
+    // only called after `try_access` checked the state exists with the right type
+    #[inline(always)]
+    fn value<'a>(&self, c: &'a Composer<N>) -> &'a T {
+        c.states[&self.id.node_key][&self.id]
+            .downcast_ref::<T>()
+            .unwrap()
+    }
+
+    #[inline(always)]
+    fn mutate<F, U>(&self, c: &mut Composer<N>, func: F) -> U
+    where
+        F: Fn(&mut T) -> U,
+    {
+        let scope_states = c.states.get_mut(&self.id.node_key).unwrap();
+        let any_state = scope_states.get_mut(&self.id).unwrap();
+        let result = func(any_state.downcast_mut::<T>().unwrap());
         c.dirty_states.insert(self.id);
-        let scope_states = c.states.entry(self.id.node_key).or_default();
-        let val = scope_states.get_mut(&self.id).unwrap();
-        *val = Box::new(value);
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    NodeDisposed(StateId),
+    TypeMismatch(StateId),
+    ComposerDropped(StateId),
+}
+
+impl StateError {
+    #[inline(always)]
+    pub fn state_id(&self) -> StateId {
+        match self {
+            StateError::NodeDisposed(id)
+            | StateError::TypeMismatch(id)
+            | StateError::ComposerDropped(id) => *id,
+        }
+    }
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let id = self.state_id();
+        match self {
+            StateError::NodeDisposed(_) => write!(
+                f,
+                "state created at {:?} was disposed with its node {}",
+                id.loc, id.node_key
+            ),
+            StateError::TypeMismatch(_) => write!(
+                f,
+                "state created at {:?} in node {} holds a different type",
+                id.loc, id.node_key
+            ),
+            StateError::ComposerDropped(_) => write!(
+                f,
+                "state created at {:?} in node {} outlived its composer",
+                id.loc, id.node_key
+            ),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+#[inline(always)]
+fn expect<U>(result: Result<U, StateError>) -> U {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
     }
 }
 
@@ -166,6 +253,16 @@ impl StateId {
             loc: Loc::new(),
         }
     }
+
+    #[inline(always)]
+    pub fn node_key(&self) -> NodeKey {
+        self.node_key
+    }
+
+    #[inline(always)]
+    pub fn loc(&self) -> Loc {
+        self.loc
+    }
 }
 
 impl Debug for StateId {
@@ -173,3 +270,87 @@ impl Debug for StateId {
         write!(f, "StateId({:?},{:?})", self.node_key, self.loc)
     }
 }
+
+pub trait MutationPolicy<T>: MaybeSendSync + 'static {
+    fn equivalent(&self, a: &T, b: &T) -> bool;
+}
+
+impl<T, F> MutationPolicy<T> for F
+where
+    F: Fn(&T, &T) -> bool + MaybeSendSync + 'static,
+{
+    #[inline(always)]
+    fn equivalent(&self, a: &T, b: &T) -> bool {
+        self(a, b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StructuralEquality;
+
+impl<T> MutationPolicy<T> for StructuralEquality
+where
+    T: PartialEq,
+{
+    #[inline(always)]
+    fn equivalent(&self, a: &T, b: &T) -> bool {
+        a == b
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReferentialEquality;
+
+impl<T> MutationPolicy<Rc<T>> for ReferentialEquality
+where
+    T: ?Sized,
+{
+    #[inline(always)]
+    fn equivalent(&self, a: &Rc<T>, b: &Rc<T>) -> bool {
+        Rc::ptr_eq(a, b)
+    }
+}
+
+impl<T> MutationPolicy<Arc<T>> for ReferentialEquality
+where
+    T: ?Sized,
+{
+    #[inline(always)]
+    fn equivalent(&self, a: &Arc<T>, b: &Arc<T>) -> bool {
+        Arc::ptr_eq(a, b)
+    }
+}
+
+impl<T> MutationPolicy<&'static T> for ReferentialEquality
+where
+    T: ?Sized,
+{
+    #[inline(always)]
+    fn equivalent(&self, a: &&'static T, b: &&'static T) -> bool {
+        std::ptr::eq(*a, *b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NeverEqual;
+
+impl<T> MutationPolicy<T> for NeverEqual {
+    #[inline(always)]
+    fn equivalent(&self, _: &T, _: &T) -> bool {
+        false
+    }
+}
+
+pub(crate) type AnyPolicy = Box<DynEq>;
+
+pub(crate) fn any_policy<T, P>(policy: P) -> AnyPolicy
+where
+    T: 'static,
+    P: MutationPolicy<T>,
+{
+    Box::new(move |a, b| {
+        let a = a.downcast_ref::<T>().unwrap();
+        let b = b.downcast_ref::<T>().unwrap();
+        policy.equivalent(a, b)
+    })
+}
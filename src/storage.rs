@@ -0,0 +1,50 @@
+#[cfg(not(feature = "sync"))]
+pub(crate) type Storage = generational_box::UnsyncStorage;
+
+#[cfg(feature = "sync")]
+pub(crate) type Storage = generational_box::SyncStorage;
+
+// everything the composer stores must be `Send + Sync` with the `sync` feature
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSendSync {}
+
+#[cfg(not(feature = "sync"))]
+impl<T> MaybeSendSync for T where T: ?Sized {}
+
+#[cfg(feature = "sync")]
+pub trait MaybeSendSync: Send + Sync {}
+
+#[cfg(feature = "sync")]
+impl<T> MaybeSendSync for T where T: Send + Sync + ?Sized {}
+
+#[cfg(not(feature = "sync"))]
+mod dyn_types {
+    use std::any::Any;
+    use std::future::Future;
+
+    pub type AnyValue = dyn Any;
+    pub type AnySendValue = dyn Any + Send;
+    pub type DynFn<R> = dyn Fn() -> R;
+    pub type DynFnOnce<R> = dyn FnOnce() -> R;
+    pub type DynEq = dyn Fn(&dyn Any, &dyn Any) -> bool;
+    pub type DynFuture = dyn Future<Output = ()>;
+}
+
+#[cfg(feature = "sync")]
+mod dyn_types {
+    use std::any::Any;
+    use std::future::Future;
+
+    pub type AnyValue = dyn Any + Send + Sync;
+    pub type AnySendValue = dyn Any + Send + Sync;
+    pub type DynFn<R> = dyn Fn() -> R + Send + Sync;
+    pub type DynFnOnce<R> = dyn FnOnce() -> R + Send + Sync;
+    pub type DynEq = dyn Fn(&dyn Any, &dyn Any) -> bool + Send + Sync;
+    pub type DynFuture = dyn Future<Output = ()> + Send + Sync;
+}
+
+pub(crate) use dyn_types::{AnySendValue, AnyValue, DynEq, DynFn, DynFnOnce, DynFuture};
+
+pub(crate) type AnyBox = Box<AnyValue>;
+
+pub(crate) type AnySendBox = Box<AnySendValue>;
@@ -1,12 +1,16 @@
-use std::hash::{Hash, Hasher};
+use std::any::Any;
+use std::cmp::Reverse;
+use std::hash::Hash;
 use std::ops::{Deref, DerefMut};
 
 use generational_box::GenerationalBox;
-use rustc_hash::FxHasher;
 
-use crate::map::Map;
+use crate::key::{hash_key, DynKey};
+use crate::map::{Map, Set};
+use crate::storage::{MaybeSendSync, Storage};
 use crate::{ComposeNode, Composer, NodeKey, Scope, ScopeId};
 
+/// A numeric slot key, it names the same slot as its raw `u64`.
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct SlotId(u64);
 
@@ -15,11 +19,6 @@ impl SlotId {
     pub fn new(raw: u64) -> Self {
         Self(raw)
     }
-
-    #[inline(always)]
-    pub fn as_usize(self) -> usize {
-        self.0 as usize
-    }
 }
 
 impl From<u64> for SlotId {
@@ -34,55 +33,270 @@ impl From<usize> for SlotId {
     }
 }
 
-impl From<&'static str> for SlotId {
-    fn from(value: &'static str) -> Self {
-        let mut hasher = FxHasher::default();
-        value.hash(&mut hasher);
-        SlotId::new(hasher.finish())
-    }
+// slot ids are stored as their raw value so both spellings find the same slot
+#[inline(always)]
+fn raw_slot_id<K>(slot_key: &K) -> Option<u64>
+where
+    K: 'static,
+{
+    (slot_key as &dyn Any)
+        .downcast_ref::<SlotId>()
+        .map(|id| id.0)
+}
+
+/// What happens to slots the host stopped requesting.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SlotRetention {
+    /// Unmount them at the end of the host's pass.
+    #[default]
+    Discard,
+    /// Keep the given number of most recently requested ones.
+    KeepRecent(usize),
+    /// Keep them until they missed more than the given number of host passes.
+    KeepPasses(usize),
+}
+
+/// What a subcompose call did with the slot's content.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlotStatus {
+    Created,
+    Recomposed,
+    Skipped,
 }
 
+// slots are numbered per host, the number doubles as the key of the slot's scope
 #[derive(Default)]
 pub(crate) struct SubcompositionEntry {
-    pub slots: Map<SlotId, SlotRecord>,
+    pub slots: Map<usize, SlotRecord>,
+    // slot numbers by key hash, keys are compared on lookup
+    index: Map<u64, Vec<usize>>,
+    next_slot: usize,
+    pub retention: SlotRetention,
+    pub pass: usize,
+    pub requests: usize,
+    pub generations: u64,
+}
+
+impl SubcompositionEntry {
+    pub(crate) fn find<K>(&self, slot_key: &K) -> Option<usize>
+    where
+        K: Hash + Eq + 'static,
+    {
+        if let Some(raw) = raw_slot_id(slot_key) {
+            return self.find(&raw);
+        }
+        let bucket = self.index.get(&hash_key(slot_key))?;
+        bucket
+            .iter()
+            .copied()
+            .find(|slot| self.slots[slot].slot_key.as_any().downcast_ref::<K>() == Some(slot_key))
+    }
+
+    #[track_caller]
+    fn find_or_insert<K>(&mut self, slot_key: K) -> usize
+    where
+        K: Hash + Eq + MaybeSendSync + 'static,
+    {
+        if let Some(raw) = raw_slot_id(&slot_key) {
+            return self.find_or_insert(raw);
+        }
+        if let Some(slot) = self.find(&slot_key) {
+            return slot;
+        }
+        // 0 is left for unkeyed scopes
+        self.next_slot += 1;
+        let slot = self.next_slot;
+        let hash = hash_key(&slot_key);
+        self.index.entry(hash).or_default().push(slot);
+        self.slots
+            .insert(slot, SlotRecord::new(Box::new(slot_key), hash));
+        slot
+    }
+
+    pub(crate) fn remove(&mut self, slot: usize) -> Option<SlotRecord> {
+        let record = self.slots.remove(&slot)?;
+        self.unindex(slot, record.hash);
+        Some(record)
+    }
+
+    fn rekey<K>(&mut self, slot: usize, slot_key: K)
+    where
+        K: Hash + Eq + MaybeSendSync + 'static,
+    {
+        if let Some(raw) = raw_slot_id(&slot_key) {
+            return self.rekey(slot, raw);
+        }
+        if let Some(existing) = self.find(&slot_key) {
+            if existing == slot {
+                return;
+            }
+            assert!(
+                self.slots[&existing].node_key.is_none(),
+                "slot key is already composed under the same host"
+            );
+            self.remove(existing);
+        }
+        let Some(record) = self.slots.get_mut(&slot) else {
+            return;
+        };
+        let old_hash = record.hash;
+        record.hash = hash_key(&slot_key);
+        record.slot_key = Box::new(slot_key);
+        let hash = record.hash;
+        self.unindex(slot, old_hash);
+        self.index.entry(hash).or_default().push(slot);
+    }
+
+    fn unindex(&mut self, slot: usize, hash: u64) {
+        if let Some(bucket) = self.index.get_mut(&hash) {
+            bucket.retain(|s| *s != slot);
+            if bucket.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+    }
+
+    // generations are counted per host so a recreated slot never repeats one
+    #[inline(always)]
+    pub(crate) fn touch(&mut self, slot: usize) {
+        if let Some(record) = self.slots.get_mut(&slot) {
+            self.generations += 1;
+            record.generation = self.generations;
+        }
+    }
+
+    // slot nodes kept alive while detached from the host's children
+    pub(crate) fn retained_nodes<'a>(
+        &'a self,
+        children: &'a [NodeKey],
+    ) -> impl Iterator<Item = NodeKey> + 'a {
+        self.slots
+            .values()
+            .filter_map(|slot| slot.node_key)
+            .filter(|node_key| !children.contains(node_key))
+    }
+
+    // called when the host finishes a pass with the children it dropped, those the
+    // retention keeps are taken out and retained nodes it no longer keeps are added,
+    // precomposed slots are left alone
+    pub(crate) fn end_pass(
+        &mut self,
+        children: &[NodeKey],
+        precomposed: Option<&Set<NodeKey>>,
+        removed: &mut Vec<NodeKey>,
+    ) {
+        let pass = self.pass;
+        let is_precomposed = |slot: &SlotRecord| {
+            slot.node_key
+                .is_some_and(|key| precomposed.is_some_and(|nodes| nodes.contains(&key)))
+        };
+        let mut idle = self
+            .slots
+            .iter()
+            .filter(|(_, slot)| slot.last_pass != pass && !is_precomposed(slot))
+            .map(|(slot, record)| (*slot, record.last_used))
+            .collect::<Vec<_>>();
+        idle.sort_by_key(|(_, last_used)| Reverse(*last_used));
+        for (rank, (slot, _)) in idle.into_iter().enumerate() {
+            let record = &self.slots[&slot];
+            let keep = match self.retention {
+                SlotRetention::Discard => false,
+                SlotRetention::KeepRecent(count) => rank < count,
+                SlotRetention::KeepPasses(passes) => pass - record.last_pass <= passes,
+            };
+            let Some(node_key) = record.node_key else {
+                self.remove(slot);
+                continue;
+            };
+            if keep {
+                removed.retain(|key| *key != node_key);
+            } else {
+                if !removed.contains(&node_key) && !children.contains(&node_key) {
+                    removed.push(node_key);
+                }
+                self.remove(slot);
+            }
+        }
+        self.pass += 1;
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
 pub(crate) struct SlotRecord {
     pub scope_id: ScopeId,
-    pub key: usize,
+    pub slot_key: Box<dyn DynKey>,
+    pub hash: u64,
     pub node_key: Option<NodeKey>,
+    pub last_pass: usize,
+    pub last_used: usize,
+    pub status: SlotStatus,
+    pub reused: bool,
+    pub generation: u64,
 }
 
 impl SlotRecord {
     #[track_caller]
-    fn new(slot_id: SlotId) -> Self {
+    fn new(slot_key: Box<dyn DynKey>, hash: u64) -> Self {
         let scope_id = ScopeId::new();
         Self {
             scope_id,
-            key: slot_id.as_usize(),
+            slot_key,
+            hash,
             node_key: None,
+            last_pass: 0,
+            last_used: 0,
+            status: SlotStatus::Created,
+            reused: false,
+            generation: 0,
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct SubcomposeHandle {
     node_key: NodeKey,
+    status: SlotStatus,
+    reused: bool,
+    generation: u64,
 }
 
 impl SubcomposeHandle {
+    fn new(node_key: NodeKey, slot: Option<&SlotRecord>) -> Self {
+        Self {
+            node_key,
+            status: slot.map_or(SlotStatus::Created, |slot| slot.status),
+            reused: slot.is_some_and(|slot| slot.reused),
+            generation: slot.map_or(0, |slot| slot.generation),
+        }
+    }
+
     #[inline(always)]
     pub fn node_key(&self) -> NodeKey {
         self.node_key
     }
+
+    #[inline(always)]
+    pub fn status(&self) -> SlotStatus {
+        self.status
+    }
+
+    /// Whether the slot's node was brought back from retention or precomposition.
+    #[inline(always)]
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    /// Changes whenever content in the slot's subtree is composed.
+    #[inline(always)]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
 }
 
 pub struct Subcomposition<N>
 where
     N: ComposeNode,
 {
-    composer: GenerationalBox<Composer<N>>,
+    composer: GenerationalBox<Composer<N>, Storage>,
     node_key: NodeKey,
 }
 
@@ -90,7 +304,7 @@ impl<N> Subcomposition<N>
 where
     N: ComposeNode,
 {
-    pub(crate) fn new(node_key: NodeKey, composer: GenerationalBox<Composer<N>>) -> Self {
+    pub(crate) fn new(node_key: NodeKey, composer: GenerationalBox<Composer<N>, Storage>) -> Self {
         {
             let mut c = composer.write();
             c.subcompositions.entry(node_key).or_default();
@@ -104,26 +318,96 @@ where
     }
 
     #[track_caller]
-    pub fn subcompose<T, C, F>(&mut self, slot_id: SlotId, ctx: C, content: F) -> SubcomposeHandle
+    pub fn subcompose<T, C, F>(
+        &mut self,
+        slot_key: impl Hash + Eq + Clone + MaybeSendSync + 'static,
+        ctx: C,
+        content: F,
+    ) -> SubcomposeHandle
     where
         T: 'static,
-        C: Clone + 'static,
-        F: Fn(SubcomposeScope<T, N, C>) + Clone + 'static,
+        C: Clone + MaybeSendSync + 'static,
+        F: Fn(SubcomposeScope<T, N, C>) + Clone + MaybeSendSync + 'static,
+    {
+        let (slot, scope_id) = self.ensure_slot(slot_key);
+        let composable = self.slot_composable(slot, scope_id, ctx, content);
+        let node_key = composable();
+        self.store_slot(slot, node_key, composable)
+    }
+
+    /// Composes a slot ahead of time without adding it to the host's children.
+    #[track_caller]
+    pub fn precompose<T, C, F>(
+        &mut self,
+        slot_key: impl Hash + Eq + Clone + MaybeSendSync + 'static,
+        ctx: C,
+        content: F,
+    ) -> PrecomposeHandle<N>
+    where
+        T: 'static,
+        C: Clone + MaybeSendSync + 'static,
+        F: Fn(SubcomposeScope<T, N, C>) + Clone + MaybeSendSync + 'static,
+    {
+        let (slot, mut scope_id) = self.ensure_slot(slot_key);
+        let composable = self.slot_composable(slot, scope_id, ctx, content);
+        let frame = {
+            let mut c = self.composer.write();
+            scope_id.set_key(slot_scope_key(&mut c, slot));
+            let slot_node_key = c
+                .subcompositions
+                .get(&self.node_key)
+                .and_then(|entry| entry.slots.get(&slot))
+                .and_then(|record| record.node_key);
+            c.begin_precompose(self.node_key, slot_node_key, scope_id)
+        };
+        let node_key = composable();
+        self.composer.write().end_precompose(frame);
+        let handle = self.store_slot(slot, node_key, composable);
+        PrecomposeHandle {
+            composer: self.composer,
+            host_node_key: self.node_key,
+            slot,
+            handle,
+        }
+    }
+
+    fn slot_composable<T, C, F>(
+        &self,
+        slot: usize,
+        scope_id: ScopeId,
+        ctx: C,
+        content: F,
+    ) -> impl Fn() -> NodeKey + Clone + MaybeSendSync + 'static
+    where
+        T: 'static,
+        C: Clone + MaybeSendSync + 'static,
+        F: Fn(SubcomposeScope<T, N, C>) + Clone + MaybeSendSync + 'static,
     {
-        let (scope_id, slot_key) = self.ensure_slot(slot_id);
         let child_scope = Scope::new(scope_id, self.composer);
         let composer = self.composer;
-        let ctx_clone = ctx.clone();
-        let content_clone = content.clone();
+        let host_node_key = self.node_key;
 
-        let composable = move || {
+        move || {
             let mut current_scope = child_scope;
             let mut skip = false;
             let (parent_node_key, current_node_key, is_dirty) = {
                 let mut c = composer.write();
-                let combined_key = combine_slot_key(slot_key, c.key_stack.last().copied());
+                let combined_key = slot_scope_key(&mut c, slot);
                 current_scope.set_key(combined_key);
                 let parent_node_key = c.current_node_key;
+                let slot_node_key = c
+                    .subcompositions
+                    .get(&host_node_key)
+                    .and_then(|entry| entry.slots.get(&slot))
+                    .and_then(|record| record.node_key);
+                let reused = slot_node_key.is_some_and(|key| {
+                    c.nodes
+                        .get(parent_node_key)
+                        .is_some_and(|parent| !parent.children.contains(&key))
+                });
+                if let Some(slot_node_key) = slot_node_key {
+                    c.attach_slot(parent_node_key, slot_node_key);
+                }
                 c.start_node(parent_node_key, current_scope.id);
                 let current_node_key = c.current_node_key;
                 let is_visited = c.composables.contains_key(&current_node_key);
@@ -132,14 +416,27 @@ where
                     c.skip_node(parent_node_key);
                     skip = true;
                 }
+                if let Some(entry) = c.subcompositions.get_mut(&host_node_key) {
+                    if !skip {
+                        entry.touch(slot);
+                    }
+                    if let Some(record) = entry.slots.get_mut(&slot) {
+                        record.status = match (is_visited, skip) {
+                            (false, _) => SlotStatus::Created,
+                            (true, false) => SlotStatus::Recomposed,
+                            (true, true) => SlotStatus::Skipped,
+                        };
+                        record.reused = reused;
+                    }
+                }
                 drop(c);
                 (parent_node_key, current_node_key, is_dirty)
             };
             if skip {
                 return current_node_key;
             }
-            let scope = SubcomposeScope::new(current_scope, ctx_clone.clone());
-            content_clone(scope);
+            let scope = SubcomposeScope::new(current_scope, ctx.clone());
+            content(scope);
             let mut c = composer.write();
             let c = c.deref_mut();
             if is_dirty {
@@ -147,27 +444,155 @@ where
             }
             c.end_node(parent_node_key);
             current_node_key
-        };
-        let node_key = composable();
-        {
-            let mut c = self.composer.write();
-            c.composables.insert(node_key, Box::new(composable));
-            if let Some(entry) = c.subcompositions.get_mut(&self.node_key) {
-                if let Some(slot) = entry.slots.get_mut(&slot_id) {
-                    slot.node_key = Some(node_key);
-                }
-            }
         }
-        SubcomposeHandle { node_key }
+    }
+
+    fn store_slot<F>(&self, slot: usize, node_key: NodeKey, composable: F) -> SubcomposeHandle
+    where
+        F: Fn() -> NodeKey + Clone + MaybeSendSync + 'static,
+    {
+        let mut c = self.composer.write();
+        c.composables.insert(node_key, Box::new(composable));
+        let record = c
+            .subcompositions
+            .get_mut(&self.node_key)
+            .and_then(|entry| entry.slots.get_mut(&slot));
+        if let Some(record) = record {
+            record.node_key = Some(node_key);
+            SubcomposeHandle::new(node_key, Some(record))
+        } else {
+            SubcomposeHandle::new(node_key, None)
+        }
+    }
+
+    /// Sets how long slots that are no longer requested keep their subtree.
+    pub fn set_retention(&mut self, retention: SlotRetention) {
+        let mut c = self.composer.write();
+        let entry = c.subcompositions.entry(self.node_key).or_default();
+        entry.retention = retention;
     }
 
     #[track_caller]
-    fn ensure_slot(&mut self, slot_id: SlotId) -> (ScopeId, usize) {
+    fn ensure_slot<K>(&mut self, slot_key: K) -> (usize, ScopeId)
+    where
+        K: Hash + Eq + MaybeSendSync + 'static,
+    {
         let mut c = self.composer.write();
         let entry = c.subcompositions.entry(self.node_key).or_default();
-        let slot_rec = SlotRecord::new(slot_id);
-        let slot = entry.slots.entry(slot_id).or_insert(slot_rec);
-        (slot.scope_id, slot.key)
+        entry.requests += 1;
+        let (pass, requests) = (entry.pass, entry.requests);
+        let slot = entry.find_or_insert(slot_key);
+        let record = entry.slots.get_mut(&slot).unwrap();
+        record.last_pass = pass;
+        record.last_used = requests;
+        (slot, record.scope_id)
+    }
+}
+
+// slot scopes composed under `Scope::key` are keyed by both keys
+#[inline(always)]
+fn slot_scope_key<N>(c: &mut Composer<N>, slot: usize) -> usize
+where
+    N: ComposeNode,
+{
+    match c.child_key() {
+        Some(parent_key) => c.keys.intern((parent_key, slot)),
+        None => c.keys.intern(slot),
+    }
+}
+
+/// A slot composed by [`Subcomposition::precompose`], kept off the host's children until attached.
+pub struct PrecomposeHandle<N>
+where
+    N: ComposeNode,
+{
+    composer: GenerationalBox<Composer<N>, Storage>,
+    host_node_key: NodeKey,
+    slot: usize,
+    handle: SubcomposeHandle,
+}
+
+impl<N> PrecomposeHandle<N>
+where
+    N: ComposeNode,
+{
+    #[inline(always)]
+    pub fn node_key(&self) -> NodeKey {
+        self.handle.node_key
+    }
+
+    #[inline(always)]
+    pub fn status(&self) -> SlotStatus {
+        self.handle.status
+    }
+
+    #[inline(always)]
+    pub fn generation(&self) -> u64 {
+        self.handle.generation
+    }
+
+    /// Whether the slot is requested under `slot_key`, which changes with [`Self::rekey`].
+    pub fn has_key<K>(&self, slot_key: &K) -> bool
+    where
+        K: Hash + Eq + 'static,
+    {
+        if let Some(raw) = raw_slot_id(slot_key) {
+            return self.has_key(&raw);
+        }
+        let c = self.composer.read();
+        c.subcompositions
+            .get(&self.host_node_key)
+            .and_then(|entry| entry.slots.get(&self.slot))
+            .is_some_and(|record| record.slot_key.as_any().downcast_ref::<K>() == Some(slot_key))
+    }
+
+    #[inline(always)]
+    pub fn is_attached(&self) -> bool {
+        let c = self.composer.read();
+        c.nodes
+            .get(self.host_node_key)
+            .is_some_and(|host| host.children.contains(&self.handle.node_key))
+    }
+
+    /// Adds the subtree to the host's children, at the current position while the host composes.
+    pub fn attach(self) -> SubcomposeHandle {
+        let mut c = self.composer.write();
+        c.attach_precomposed(self.host_node_key, self.handle.node_key);
+        if let Some(entry) = c.subcompositions.get_mut(&self.host_node_key) {
+            let pass = entry.pass;
+            if let Some(record) = entry.slots.get_mut(&self.slot) {
+                record.last_pass = pass;
+                record.reused = true;
+            }
+        }
+        SubcomposeHandle {
+            reused: true,
+            ..self.handle
+        }
+    }
+
+    /// Moves the subtree to another slot, so it is reused when that slot is requested.
+    pub fn rekey(&mut self, slot_key: impl Hash + Eq + Clone + MaybeSendSync + 'static) {
+        let mut c = self.composer.write();
+        if let Some(entry) = c.subcompositions.get_mut(&self.host_node_key) {
+            entry.rekey(self.slot, slot_key);
+        }
+    }
+
+    /// Unmounts the subtree unless it was attached.
+    pub fn dispose(self) {
+        let mut c = self.composer.write();
+        let precomposed = c
+            .precomposed
+            .get(&self.host_node_key)
+            .is_some_and(|nodes| nodes.contains(&self.handle.node_key));
+        if !precomposed {
+            return;
+        }
+        c.unmount_node(self.handle.node_key);
+        if let Some(entry) = c.subcompositions.get_mut(&self.host_node_key) {
+            entry.remove(self.slot);
+        }
     }
 }
 
@@ -188,13 +613,18 @@ where
 
     #[inline(always)]
     #[track_caller]
-    pub fn subcompose<T, C, F>(&mut self, slot_id: SlotId, ctx: C, content: F) -> SubcomposeHandle
+    pub fn subcompose<T, C, F>(
+        &mut self,
+        slot_key: impl Hash + Eq + Clone + MaybeSendSync + 'static,
+        ctx: C,
+        content: F,
+    ) -> SubcomposeHandle
     where
         T: 'static,
-        C: Clone + 'static,
-        F: Fn(SubcomposeScope<T, N, C>) + Clone + 'static,
+        C: Clone + MaybeSendSync + 'static,
+        F: Fn(SubcomposeScope<T, N, C>) + Clone + MaybeSendSync + 'static,
     {
-        self.host.subcompose(slot_id, ctx, content)
+        self.host.subcompose(slot_key, ctx, content)
     }
 }
 
@@ -240,8 +670,3 @@ where
         &self.scope
     }
 }
-
-#[inline(always)]
-fn combine_slot_key(base: usize, parent: Option<usize>) -> usize {
-    parent.map(|p| p ^ base).unwrap_or(base)
-}
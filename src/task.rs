@@ -0,0 +1,11 @@
+use std::pin::Pin;
+
+use crate::storage::{AnyBox, DynFuture};
+
+pub(crate) type TaskFuture = Pin<Box<DynFuture>>;
+
+pub(crate) struct TaskSlot {
+    pub keys: AnyBox,
+    // `None` once the task completed, the slot stays so it is not relaunched
+    pub future: Option<TaskFuture>,
+}
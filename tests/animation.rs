@@ -0,0 +1,180 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{
+    AnimationSpec, ComposeNode, Composer, Easing, Interpolate, Recomposer, Root, Scope, State,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+const MS: u64 = 1_000_000;
+
+struct Host;
+struct Reader;
+
+#[derive(Clone)]
+struct Probe<T> {
+    target: Shared<Option<State<T, TestNode>>>,
+    host_runs: Shared<usize>,
+    reader_runs: Shared<usize>,
+    seen: Shared<T>,
+}
+
+impl<T: Interpolate + Copy + Default> Probe<T> {
+    fn new() -> Self {
+        Self {
+            target: Shared::default(),
+            host_runs: Shared::default(),
+            reader_runs: Shared::default(),
+            seen: Shared::default(),
+        }
+    }
+}
+
+fn compose<T>(probe: &Probe<T>, spec: AnimationSpec<T>) -> Recomposer<(), TestNode>
+where
+    T: Interpolate + Copy + Default,
+{
+    let probe = probe.clone();
+    Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let (probe, spec) = (probe.clone(), spec.clone());
+            scope.create_node(
+                scope.child::<Host>(),
+                move |scope| {
+                    probe.host_runs.set(probe.host_runs.get() + 1);
+                    let target = scope.use_state(T::default);
+                    probe.target.set(Some(target));
+                    let animated = scope.animate_as_state(target.get(), spec.clone());
+                    let probe = probe.clone();
+                    scope.create_node(
+                        scope.child::<Reader>(),
+                        move |_| {
+                            probe.reader_runs.set(probe.reader_runs.get() + 1);
+                            probe.seen.set(animated.get());
+                        },
+                        || (),
+                        |_, _| TestNode,
+                        |_, _, _| {},
+                    );
+                },
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        (),
+    )
+}
+
+#[test]
+fn tween_moves_towards_target_and_stops() {
+    let probe = Probe::<f32>::new();
+    let mut recomposer = compose(&probe, AnimationSpec::tween_with(100 * MS, Easing::Linear));
+    probe.target.get().unwrap().set(10.0);
+    recomposer.recompose();
+    assert!(recomposer.is_animating());
+    assert_eq!(probe.seen.get(), 0.0);
+
+    recomposer.advance_frame(50 * MS);
+    assert_eq!(probe.seen.get(), 5.0);
+    // frames only recompose readers of the animated value
+    assert_eq!(probe.host_runs.get(), 2);
+    assert_eq!(probe.reader_runs.get(), 2);
+
+    recomposer.advance_frame(120 * MS);
+    assert_eq!(probe.seen.get(), 10.0);
+    assert!(!recomposer.is_animating());
+    recomposer.advance_frame(140 * MS);
+    assert_eq!(probe.reader_runs.get(), 3);
+}
+
+#[test]
+fn retarget_starts_from_current_value() {
+    let probe = Probe::<f32>::new();
+    let mut recomposer = compose(&probe, AnimationSpec::tween_with(100 * MS, Easing::Linear));
+    probe.target.get().unwrap().set(10.0);
+    recomposer.recompose();
+    recomposer.advance_frame(50 * MS);
+
+    probe.target.get().unwrap().set(0.0);
+    recomposer.recompose();
+    recomposer.advance_frame(100 * MS);
+    assert_eq!(probe.seen.get(), 2.5);
+}
+
+#[test]
+fn spring_overshoots_then_settles() {
+    let probe = Probe::<f32>::new();
+    let mut recomposer = compose(&probe, AnimationSpec::spring(400.0, 0.3));
+    probe.target.get().unwrap().set(1.0);
+    recomposer.recompose();
+
+    let mut peak = 0.0f32;
+    let mut frame = 0;
+    while recomposer.is_animating() {
+        frame += 16 * MS;
+        recomposer.advance_frame(frame);
+        peak = peak.max(probe.seen.get());
+        assert!(frame < 10_000 * MS, "spring never settled");
+    }
+    assert!(peak > 1.0);
+    assert_eq!(probe.seen.get(), 1.0);
+}
+
+fn settle_time(spec: AnimationSpec<f32>) -> u64 {
+    let probe = Probe::<f32>::new();
+    let mut recomposer = compose(&probe, spec);
+    probe.target.get().unwrap().set(1.0);
+    recomposer.recompose();
+
+    let mut frame = 0;
+    while recomposer.is_animating() {
+        frame += 16 * MS;
+        recomposer.advance_frame(frame);
+        assert!(probe.seen.get().is_finite());
+        assert!(frame < 60_000 * MS, "spring never settled");
+    }
+    assert_eq!(probe.seen.get(), 1.0);
+    frame
+}
+
+#[test]
+fn undamped_spring_settles() {
+    settle_time(AnimationSpec::spring(400.0, 0.0));
+    settle_time(AnimationSpec::Spring {
+        stiffness: 400.0,
+        damping_ratio: -1.0,
+    });
+}
+
+#[test]
+fn zero_stiffness_spring_settles() {
+    settle_time(AnimationSpec::spring(0.0, 1.0));
+    settle_time(AnimationSpec::Spring {
+        stiffness: f32::NAN,
+        damping_ratio: 0.5,
+    });
+}
+
+#[test]
+fn keyframes_interpolate_tuples() {
+    let probe = Probe::<(f32, f32)>::new();
+    let spec = AnimationSpec::keyframes(100 * MS, vec![(20 * MS, (10.0, -10.0))]);
+    let mut recomposer = compose(&probe, spec);
+    probe.target.get().unwrap().set((20.0, 20.0));
+    recomposer.recompose();
+
+    recomposer.advance_frame(10 * MS);
+    assert_eq!(probe.seen.get(), (5.0, -5.0));
+    recomposer.advance_frame(60 * MS);
+    assert_eq!(probe.seen.get(), (15.0, 5.0));
+    recomposer.advance_frame(100 * MS);
+    assert_eq!(probe.seen.get(), (20.0, 20.0));
+    assert!(!recomposer.is_animating());
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::Shared;
+use compose_rt::{Applier, ComposeNode, Composer, NodeKey, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(String);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+
+struct Item;
+struct List;
+
+#[derive(Default)]
+struct Mirror {
+    children: HashMap<NodeKey, Vec<NodeKey>>,
+    data: HashMap<NodeKey, TestNode>,
+    frames: usize,
+}
+
+#[derive(Clone, Default)]
+struct MirrorApplier(Shared<Mirror>);
+
+impl Applier<TestNode> for MirrorApplier {
+    fn on_end(&mut self) {
+        self.0.borrow_mut().frames += 1;
+    }
+
+    fn insert_at(&mut self, parent: NodeKey, index: usize, node_key: NodeKey) {
+        let mut mirror = self.0.borrow_mut();
+        mirror
+            .children
+            .entry(parent)
+            .or_default()
+            .insert(index, node_key);
+    }
+
+    fn remove(&mut self, parent: NodeKey, index: usize, node_key: NodeKey) {
+        let mut mirror = self.0.borrow_mut();
+        let removed = mirror.children.get_mut(&parent).unwrap().remove(index);
+        assert_eq!(removed, node_key);
+        mirror.children.remove(&node_key);
+        mirror.data.remove(&node_key);
+    }
+
+    fn move_node(&mut self, parent: NodeKey, from: usize, to: usize, node_key: NodeKey) {
+        let mut mirror = self.0.borrow_mut();
+        let siblings = mirror.children.get_mut(&parent).unwrap();
+        let moved = siblings.remove(from);
+        assert_eq!(moved, node_key);
+        siblings.insert(to, moved);
+    }
+
+    fn update(&mut self, node_key: NodeKey, node: &TestNode) {
+        self.0.borrow_mut().data.insert(node_key, node.clone());
+    }
+}
+
+fn item<S: 'static>(scope: TestScope<S>, label: String) {
+    scope.create_node(
+        scope.child::<Item>(),
+        |_| {},
+        move || label.clone(),
+        |label, _| TestNode(label),
+        |node, label, _| node.0 = label,
+    );
+}
+
+fn app(scope: TestScope<Root>, items: State<Vec<usize>, TestNode>) {
+    scope.create_node(
+        scope.child::<List>(),
+        move |scope| {
+            for i in items.get() {
+                scope.key(i, move |scope| item(scope, format!("item {}", i)));
+            }
+        },
+        move || format!("list of {}", items.get().len()),
+        |label, _| TestNode(label),
+        |node, label, _| node.0 = label,
+    );
+}
+
+fn assert_mirrored(recomposer: &Recomposer<Vec<usize>, TestNode>, mirror: &Mirror) {
+    recomposer.with_composer(|composer| {
+        let mut stack = vec![composer.root_node_key()];
+        let mut visited = 0;
+        while let Some(node_key) = stack.pop() {
+            let node = &composer.nodes[node_key];
+            let children = mirror.children.get(&node_key).cloned().unwrap_or_default();
+            assert_eq!(node.children, children);
+            assert_eq!(node.data.as_ref(), mirror.data.get(&node_key));
+            stack.extend(node.children.iter().copied());
+            visited += 1;
+        }
+        assert_eq!(visited, mirror.data.len());
+    });
+}
+
+#[test]
+fn applier_mirrors_tree_changes() {
+    let mut recomposer = Composer::compose_with(app, (), || vec![1, 2, 3]);
+    let applier = MirrorApplier::default();
+    recomposer.set_applier(applier.clone());
+    assert_mirrored(&recomposer, &applier.0.borrow());
+
+    for items in [vec![1, 2, 3, 4], vec![1, 4], vec![5, 1, 4], vec![]] {
+        recomposer.recompose_with(items);
+        assert_mirrored(&recomposer, &applier.0.borrow());
+    }
+    assert_eq!(applier.0.borrow().frames, 5);
+}
@@ -0,0 +1,162 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{BoundaryError, ComposeNode, Composer, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(&'static str);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Item;
+
+#[derive(Clone, Default)]
+struct Handles {
+    fail: Shared<Option<State<bool, TestNode>>>,
+    error: Shared<Option<BoundaryError<TestNode>>>,
+    effects: Shared<usize>,
+}
+
+fn item<S: 'static>(scope: Scope<S, TestNode>, name: &'static str) {
+    scope.create_node(
+        scope.child::<Item>(),
+        |_| {},
+        || (),
+        move |_, _| TestNode(name),
+        move |n, _, _| n.0 = name,
+    );
+}
+
+fn app(scope: Scope<Root, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Root>(),
+        move |scope| {
+            let fail = scope.use_state(|| false);
+            handles.fail.set(Some(fail));
+            item(scope, "before");
+            let (h, e) = (handles.clone(), handles.error.clone());
+            scope.error_boundary(
+                move |scope| {
+                    item(scope, "ok");
+                    let effects = h.effects.clone();
+                    scope.create_node(
+                        scope.child::<Item>(),
+                        move |scope| {
+                            let effects = effects.clone();
+                            scope.use_effect((), move || effects.set(effects.get() + 1));
+                            item(scope, "nested");
+                            if fail.get() {
+                                item(scope, "partial");
+                                panic!("boom");
+                            }
+                        },
+                        || (),
+                        |_, _| TestNode("inner"),
+                        |_, _, _| {},
+                    );
+                },
+                move |scope, error| {
+                    *e.borrow_mut() = Some(error);
+                    item(scope, "fallback");
+                },
+            );
+            item(scope, "after");
+        },
+        || (),
+        |_, _| TestNode("root"),
+        |_, _, _| {},
+    );
+}
+
+fn names(recomposer: &Recomposer<(), TestNode>) -> Vec<&'static str> {
+    let mut names = recomposer.with_composer(|c| {
+        c.nodes
+            .iter()
+            .filter_map(|(_, n)| n.data.as_ref().map(|d| d.0))
+            .collect::<Vec<_>>()
+    });
+    names.sort();
+    names
+}
+
+#[test]
+fn panic_renders_fallback_and_discards_partial_subtree() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone()), ());
+    assert_eq!(
+        names(&recomposer),
+        ["after", "before", "inner", "nested", "ok", "root"]
+    );
+
+    handles.fail.get().unwrap().set(true);
+    let report = recomposer.recompose();
+    assert_eq!(names(&recomposer), ["after", "before", "fallback", "root"]);
+    let error = handles.error.borrow().clone().unwrap();
+    assert_eq!(error.message(), "boom");
+    // nodes built by the failed attempt never show up as mounted
+    assert_eq!(report.mounted.len(), 1);
+    assert_eq!(report.unmounted.len(), 3);
+
+    // the composer stays usable
+    recomposer.recompose();
+    assert_eq!(names(&recomposer), ["after", "before", "fallback", "root"]);
+}
+
+#[test]
+fn retry_composes_content_again() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone()), ());
+    handles.fail.get().unwrap().set(true);
+    recomposer.recompose();
+    assert_eq!(handles.effects.get(), 1);
+
+    handles.fail.get().unwrap().set(false);
+    handles.error.borrow().as_ref().unwrap().retry();
+    recomposer.recompose();
+    assert_eq!(
+        names(&recomposer),
+        ["after", "before", "inner", "nested", "ok", "root"]
+    );
+    assert_eq!(handles.effects.get(), 2);
+}
+
+#[test]
+fn panic_during_first_composition() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose(
+        move |scope| {
+            let h = h.clone();
+            scope.create_node(
+                scope.child::<Root>(),
+                move |scope| {
+                    let e = h.error.clone();
+                    scope.error_boundary(
+                        |scope| {
+                            item(scope, "ok");
+                            panic!("{} failed", "first");
+                        },
+                        move |scope, error| {
+                            *e.borrow_mut() = Some(error);
+                            item(scope, "fallback");
+                        },
+                    );
+                },
+                || (),
+                |_, _| TestNode("root"),
+                |_, _, _| {},
+            );
+        },
+        (),
+    );
+    assert_eq!(names(&recomposer), ["fallback", "root"]);
+    assert_eq!(
+        handles.error.borrow().as_ref().unwrap().message(),
+        "first failed"
+    );
+    recomposer.recompose();
+}
@@ -0,0 +1,205 @@
+mod common;
+
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use common::Shared;
+use compose_rt::{Applier, ComposeNode, Composer, NodeKey, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Row;
+struct Footer;
+
+type StateCell = Shared<Option<State<usize, TestNode>>>;
+
+#[derive(Clone, Default)]
+struct Handles {
+    rows: StateCell,
+    tick: StateCell,
+    footer: StateCell,
+    row_runs: Shared<usize>,
+}
+
+fn app(scope: Scope<Root, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Row>(),
+        move |scope| {
+            let tick = scope.use_state(|| 0);
+            let footer = scope.use_state(|| 0);
+            handles.tick.set(Some(tick));
+            handles.footer.set(Some(footer));
+            rows(scope, handles.clone(), tick);
+            scope.create_node(
+                scope.child::<Footer>(),
+                move |_| {
+                    footer.get();
+                },
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn rows(scope: Scope<Row, TestNode>, handles: Handles, tick: State<usize, TestNode>) {
+    scope.create_node(
+        scope.child::<Row>(),
+        move |scope| {
+            let rows = scope.use_state(|| 8);
+            handles.rows.set(Some(rows));
+            for i in 0..rows.get() {
+                let runs = handles.row_runs.clone();
+                scope.key(i, move |scope| {
+                    let runs = runs.clone();
+                    scope.create_node(
+                        scope.child::<Row>(),
+                        move |_| {
+                            tick.get();
+                            runs.set(runs.get() + 1);
+                        },
+                        || (),
+                        |_, _| TestNode,
+                        |_, _, _| {},
+                    );
+                });
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(handles: &Handles) -> Recomposer<(), TestNode> {
+    let h = handles.clone();
+    Composer::compose(move |s| app(s, h.clone()), ())
+}
+
+fn row_count(recomposer: &Recomposer<(), TestNode>) -> usize {
+    recomposer.with_composer(|c| c.nodes.len())
+}
+
+#[test]
+fn expired_deadline_runs_one_node_per_call() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    handles.row_runs.set(0);
+    handles.tick.get().unwrap().set(1);
+
+    let mut slices = 0;
+    let report = loop {
+        slices += 1;
+        match recomposer.recompose_with_budget(Instant::now()) {
+            Poll::Ready(report) => break report,
+            Poll::Pending => assert!(recomposer.is_recomposing()),
+        }
+    };
+    assert_eq!(slices, 8);
+    assert_eq!(handles.row_runs.get(), 8);
+    assert_eq!(report.recomposed.len(), 8);
+    assert!(!recomposer.is_recomposing());
+}
+
+#[test]
+fn unmounts_wait_for_the_pass_to_complete() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let nodes = row_count(&recomposer);
+    handles.rows.get().unwrap().set(6);
+    handles.footer.get().unwrap().set(1);
+
+    // the footer is left, so the pass pauses before discarding the dropped rows
+    assert!(recomposer
+        .recompose_with_budget(Instant::now())
+        .is_pending());
+    assert_eq!(row_count(&recomposer), nodes);
+
+    // a plain recompose finishes the paused pass
+    let report = recomposer.recompose();
+    assert_eq!(report.unmounted.len(), 2);
+    assert_eq!(report.recomposed.len(), 2);
+    assert_eq!(row_count(&recomposer), nodes - 2);
+}
+
+#[test]
+fn detached_dirty_nodes_do_not_pause_the_pass() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let nodes = row_count(&recomposer);
+    handles.rows.get().unwrap().set(6);
+    handles.tick.get().unwrap().set(1);
+
+    // the dropped rows are dirty too, but the list covered everything still attached
+    let Poll::Ready(report) = recomposer.recompose_with_budget(Instant::now()) else {
+        panic!("pass paused with no attached dirty node left");
+    };
+    assert_eq!(report.unmounted.len(), 2);
+    assert_eq!(report.recomposed.len(), 7);
+    assert_eq!(row_count(&recomposer), nodes - 2);
+}
+
+#[derive(Clone, Default)]
+struct Events(Shared<Vec<&'static str>>);
+
+impl Applier<TestNode> for Events {
+    fn on_begin(&mut self) {
+        self.0.borrow_mut().push("begin");
+    }
+
+    fn on_end(&mut self) {
+        self.0.borrow_mut().push("end");
+    }
+
+    fn insert_at(&mut self, _: NodeKey, _: usize, _: NodeKey) {
+        self.0.borrow_mut().push("insert");
+    }
+
+    fn remove(&mut self, _: NodeKey, _: usize, _: NodeKey) {
+        self.0.borrow_mut().push("remove");
+    }
+
+    fn move_node(&mut self, _: NodeKey, _: usize, _: usize, _: NodeKey) {
+        self.0.borrow_mut().push("move");
+    }
+
+    fn update(&mut self, _: NodeKey, _: &TestNode) {}
+}
+
+#[test]
+fn paused_pass_holds_back_the_applier() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let events = Events::default();
+    recomposer.set_applier(events.clone());
+    events.0.borrow_mut().clear();
+    handles.rows.get().unwrap().set(6);
+    handles.footer.get().unwrap().set(1);
+
+    // the rows dropped so far are not sent while the pass is paused
+    assert!(recomposer
+        .recompose_with_budget(Instant::now())
+        .is_pending());
+    assert!(events.0.borrow().is_empty());
+
+    assert!(recomposer.recompose_with_budget(Instant::now()).is_ready());
+    assert_eq!(*events.0.borrow(), ["begin", "remove", "remove", "end"]);
+}
+
+#[test]
+fn generous_budget_completes_in_one_call() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    handles.tick.get().unwrap().set(1);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    assert!(recomposer.recompose_with_budget(deadline).is_ready());
+}
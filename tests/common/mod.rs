@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+
+use std::mem;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+// stands in for `Rc<Cell<T>>` and `Rc<RefCell<T>>` in test probes, so the composables
+// capturing them are `Send + Sync` when the sync feature needs them to be
+pub struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.borrow().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.borrow_mut() = value;
+    }
+
+    pub fn take(&self) -> T
+    where
+        T: Default,
+    {
+        mem::take(&mut *self.borrow_mut())
+    }
+
+    // a panicking composable must not poison the probes for the assertions after it
+    pub fn borrow(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn borrow_mut(&self) -> MutexGuard<'_, T> {
+        self.borrow()
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, DerivedState, MaybeSendSync, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(String);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+
+struct Container;
+struct Label;
+
+fn label<S, T>(scope: TestScope<S>, value: DerivedState<T, TestNode>, runs: Shared<usize>)
+where
+    S: 'static,
+    T: PartialEq + ToString + Clone + MaybeSendSync + 'static,
+{
+    scope.create_node(
+        scope.child::<Label>(),
+        move |_| runs.set(runs.get() + 1),
+        move || value.get().to_string(),
+        |text, _| TestNode(text),
+        |node, text, _| node.0 = text,
+    );
+}
+
+fn app(
+    scope: TestScope<Root>,
+    items: State<Vec<i32>, TestNode>,
+    count_runs: Shared<usize>,
+    even_runs: Shared<usize>,
+) {
+    scope.create_node(
+        scope.child::<Container>(),
+        move |scope| {
+            let positives = scope
+                .derived_state_of(move || items.with(|i| i.iter().filter(|v| **v > 0).count()));
+            let is_even = scope.derived_state_of(move || positives.get() % 2 == 0);
+            label(scope, positives, count_runs.clone());
+            label(scope, is_even, even_runs.clone());
+        },
+        || (),
+        |_, _| TestNode("container".to_string()),
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn derived_state_only_invalidates_readers_on_change() {
+    let count_runs = Shared::new(0);
+    let even_runs = Shared::new(0);
+    let (c, e) = (count_runs.clone(), even_runs.clone());
+    let mut recomposer = Composer::compose_with(
+        move |s, items| app(s, items, c.clone(), e.clone()),
+        (),
+        || vec![1, -2, 3],
+    );
+    assert_eq!((count_runs.get(), even_runs.get()), (1, 1));
+
+    // same number of positives
+    recomposer.recompose_with(vec![-1, 5, 6]);
+    assert_eq!((count_runs.get(), even_runs.get()), (1, 1));
+
+    // count changes but parity does not
+    recomposer.recompose_with(vec![1, 2, 3, 4]);
+    assert_eq!((count_runs.get(), even_runs.get()), (2, 1));
+
+    recomposer.recompose_with(vec![1]);
+    assert_eq!((count_runs.get(), even_runs.get()), (3, 2));
+    recomposer.with_composer(|c| {
+        let mut labels = c
+            .nodes
+            .iter()
+            .filter_map(|(_, n)| n.data.as_ref().map(|d| d.0.clone()))
+            .collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, ["1", "container", "false"]);
+    });
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+type Log = Shared<Vec<String>>;
+
+struct Container;
+struct Subscriber;
+
+fn subscriber<S: 'static>(scope: TestScope<S>, topic: State<usize, TestNode>, log: Log) {
+    scope.create_node(
+        scope.child::<Subscriber>(),
+        move |scope| {
+            let topic = topic.get();
+            let log = log.clone();
+            scope.use_disposable_effect(topic, move || {
+                log.borrow_mut().push(format!("subscribe {}", topic));
+                move || log.borrow_mut().push(format!("unsubscribe {}", topic))
+            });
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn app(scope: TestScope<Root>, show: State<bool, TestNode>, log: Log) {
+    scope.create_node(
+        scope.child::<Container>(),
+        move |scope| {
+            let topic = scope.use_state(|| 1usize);
+            let log_for_side = log.clone();
+            scope.use_side_effect(move || log_for_side.borrow_mut().push("side".to_string()));
+            if show.get() {
+                subscriber(scope, topic, log.clone());
+            }
+            let log_for_effect = log.clone();
+            scope.use_effect((), move || {
+                topic.set(2);
+                log_for_effect.borrow_mut().push("mounted".to_string());
+            });
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn take(log: &Log) -> Vec<String> {
+    log.borrow_mut().drain(..).collect()
+}
+
+#[test]
+fn effects_run_after_commit_and_dispose_on_unmount() {
+    let log = Log::default();
+    let app_log = log.clone();
+    let mut recomposer =
+        Composer::compose_with(move |s, show| app(s, show, app_log.clone()), (), || true);
+    assert_eq!(take(&log), ["side", "subscribe 1", "mounted"]);
+
+    // `topic` was set by the mount effect
+    recomposer.recompose();
+    assert_eq!(take(&log), ["unsubscribe 1", "subscribe 2"]);
+
+    recomposer.recompose();
+    assert!(take(&log).is_empty());
+
+    recomposer.recompose_with(false);
+    assert_eq!(take(&log), ["unsubscribe 2", "side"]);
+
+    recomposer.recompose_with(true);
+    assert_eq!(take(&log), ["side", "subscribe 2"]);
+
+    drop(recomposer);
+    assert_eq!(take(&log), ["unsubscribe 2"]);
+}
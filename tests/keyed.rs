@@ -0,0 +1,308 @@
+mod common;
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, NodeKey, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(usize);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+
+struct List;
+struct Item;
+
+fn app(scope: TestScope<Root>, items: State<Vec<usize>, TestNode>) {
+    scope.create_node(
+        scope.child::<List>(),
+        move |scope| {
+            for i in items.get() {
+                scope.key(i, move |scope| {
+                    scope.create_node(
+                        scope.child::<Item>(),
+                        move |scope| {
+                            let _ = scope.use_state(move || i);
+                        },
+                        move || i,
+                        |i, _| TestNode(i),
+                        |node, i, _| node.0 = i,
+                    );
+                });
+            }
+        },
+        || (),
+        |_, _| TestNode(usize::MAX),
+        |_, _, _| {},
+    );
+}
+
+fn items(recomposer: &Recomposer<Vec<usize>, TestNode>) -> Vec<(usize, NodeKey)> {
+    recomposer.with_composer(|c| {
+        let list = &c.nodes[c.root_node_key()];
+        list.children
+            .iter()
+            .map(|key| (c.nodes[*key].data.as_ref().unwrap().0, *key))
+            .collect()
+    })
+}
+
+#[test]
+fn keyed_children_move_instead_of_remount() {
+    let mut recomposer = Composer::compose_with(app, (), || vec![1, 2, 3, 4]);
+    let initial = items(&recomposer).into_iter().collect::<HashMap<_, _>>();
+    let node_count = recomposer.with_composer(|c| c.nodes.len());
+
+    for order in [vec![4, 3, 2, 1], vec![2, 4, 1, 3], vec![3, 1, 2, 4]] {
+        recomposer.recompose_with(order.clone());
+        let current = items(&recomposer);
+        assert_eq!(current.iter().map(|(i, _)| *i).collect::<Vec<_>>(), order);
+        for (i, key) in current {
+            assert_eq!(initial[&i], key);
+        }
+        assert_eq!(recomposer.with_composer(|c| c.nodes.len()), node_count);
+    }
+
+    recomposer.recompose_with(vec![5, 3, 1]);
+    let current = items(&recomposer);
+    assert_eq!(
+        current.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        [5, 3, 1]
+    );
+    assert_eq!(current[1].1, initial[&3]);
+    assert_eq!(current[2].1, initial[&1]);
+    assert_eq!(recomposer.with_composer(|c| c.nodes.len()), node_count - 1);
+}
+
+fn named(scope: TestScope<Root>, names: State<Vec<&'static str>, TestNode>) {
+    scope.create_node(
+        scope.child::<List>(),
+        move |scope| {
+            for (i, name) in names.get().into_iter().enumerate() {
+                scope.key(name.to_string(), move |scope| {
+                    scope.create_node(
+                        scope.child::<Item>(),
+                        |_| {},
+                        move || i,
+                        |i, _| TestNode(i),
+                        |node, i, _| node.0 = i,
+                    );
+                });
+            }
+        },
+        || (),
+        |_, _| TestNode(usize::MAX),
+        |_, _, _| {},
+    );
+}
+
+fn child_keys(recomposer: &Recomposer<Vec<&'static str>, TestNode>) -> Vec<NodeKey> {
+    recomposer.with_composer(|c| c.nodes[c.root_node_key()].children.clone())
+}
+
+#[test]
+fn string_keys_are_matched_by_value() {
+    let mut recomposer = Composer::compose_with(named, (), || vec!["a", "b", "c"]);
+    let initial = child_keys(&recomposer);
+
+    recomposer.recompose_with(vec!["c", "a", "b"]);
+    let reordered = child_keys(&recomposer);
+    assert_eq!(reordered, [initial[2], initial[0], initial[1]]);
+}
+
+#[test]
+#[should_panic(expected = "duplicate key for sibling composed at tests/keyed.rs")]
+fn duplicate_sibling_keys_are_reported() {
+    let _ = Composer::compose_with(named, (), || vec!["a", "b", "a"]);
+}
+
+// counts the live copies of a key, equal by `id` only
+struct Tracked {
+    id: usize,
+    live: Arc<AtomicUsize>,
+}
+
+impl Tracked {
+    fn new(id: usize, live: &Arc<AtomicUsize>) -> Self {
+        live.fetch_add(1, Ordering::SeqCst);
+        Self {
+            id,
+            live: live.clone(),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.live.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl PartialEq for Tracked {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Tracked {}
+
+impl Hash for Tracked {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+#[test]
+fn keys_are_released_with_their_nodes() {
+    let live = Arc::new(AtomicUsize::new(0));
+    let l = live.clone();
+    let mut recomposer = Composer::compose_with(
+        move |scope, items: State<Vec<usize>, TestNode>| {
+            let l = l.clone();
+            scope.create_node(
+                scope.child::<List>(),
+                move |scope| {
+                    for i in items.get() {
+                        scope.key(Tracked::new(i, &l), move |scope| {
+                            scope.create_node(
+                                scope.child::<Item>(),
+                                |_| {},
+                                move || i,
+                                |i, _| TestNode(i),
+                                |node, i, _| node.0 = i,
+                            );
+                        });
+                    }
+                    // keys without a node of their own
+                    scope.key(Tracked::new(usize::MAX, &l), |_| {});
+                },
+                || (),
+                |_, _| TestNode(usize::MAX),
+                |_, _, _| {},
+            );
+        },
+        (),
+        || vec![1, 2, 3, 4],
+    );
+    assert_eq!(live.load(Ordering::SeqCst), 4);
+
+    recomposer.recompose_with(vec![4, 2]);
+    assert_eq!(live.load(Ordering::SeqCst), 2);
+
+    recomposer.recompose_with(vec![2, 5]);
+    assert_eq!(live.load(Ordering::SeqCst), 2);
+
+    recomposer.recompose_with(Vec::new());
+    assert_eq!(live.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn same_site_children_share_a_key_block() {
+    let mut recomposer = Composer::compose_with(
+        |scope, items: State<Vec<usize>, TestNode>| {
+            scope.create_node(
+                scope.child::<List>(),
+                move |scope| {
+                    for i in items.get() {
+                        scope.key(i, move |scope| {
+                            for j in 0..2 {
+                                scope.create_node(
+                                    scope.child::<Item>(),
+                                    |_| {},
+                                    move || i * 10 + j,
+                                    |i, _| TestNode(i),
+                                    |node, i, _| node.0 = i,
+                                );
+                            }
+                        });
+                    }
+                },
+                || (),
+                |_, _| TestNode(usize::MAX),
+                |_, _, _| {},
+            );
+        },
+        (),
+        || vec![1, 2],
+    );
+    let labels = |recomposer: &Recomposer<Vec<usize>, TestNode>| {
+        recomposer.with_composer(|c| {
+            c.nodes[c.root_node_key()]
+                .children
+                .iter()
+                .map(|key| c.nodes[*key].data.as_ref().unwrap().0)
+                .collect::<Vec<_>>()
+        })
+    };
+    assert_eq!(labels(&recomposer), [10, 11, 20, 21]);
+
+    recomposer.recompose_with(vec![2, 1]);
+    assert_eq!(labels(&recomposer), [20, 21, 10, 11]);
+}
+
+#[test]
+fn keyed_item_recomposed_on_its_own_keeps_its_children() {
+    let created = Arc::new(AtomicUsize::new(0));
+    let tick = Shared::new(None);
+    let (c, t) = (created.clone(), tick.clone());
+    let mut recomposer = Composer::compose(
+        move |scope: TestScope<Root>| {
+            let (c, t) = (c.clone(), t.clone());
+            scope.create_node(
+                scope.child::<List>(),
+                move |scope| {
+                    let (c, t) = (c.clone(), t.clone());
+                    scope.key(1, move |scope| {
+                        let (c, t) = (c.clone(), t.clone());
+                        scope.create_node(
+                            scope.child::<Item>(),
+                            move |scope| {
+                                let state = scope.use_state(|| 0);
+                                state.get();
+                                t.set(Some(state));
+                                let c = c.clone();
+                                scope.create_node(
+                                    scope.child::<Item>(),
+                                    move |scope| {
+                                        let c = c.clone();
+                                        scope.use_state(move || c.fetch_add(1, Ordering::SeqCst));
+                                    },
+                                    || 0,
+                                    |i, _| TestNode(i),
+                                    |node, i, _| node.0 = i,
+                                );
+                            },
+                            || 1,
+                            |i, _| TestNode(i),
+                            |node, i, _| node.0 = i,
+                        );
+                    });
+                },
+                || (),
+                |_, _| TestNode(usize::MAX),
+                |_, _, _| {},
+            );
+        },
+        (),
+    );
+    let leaf = |recomposer: &Recomposer<(), TestNode>| {
+        recomposer.with_composer(|c| {
+            let item = c.nodes[c.root_node_key()].children[0];
+            c.nodes[item].children[0]
+        })
+    };
+    let before = leaf(&recomposer);
+
+    tick.get().unwrap().set(1);
+    let report = recomposer.recompose();
+    assert_eq!(report.recomposed.len(), 1);
+    assert_eq!(leaf(&recomposer), before);
+    assert_eq!(created.load(Ordering::SeqCst), 1);
+}
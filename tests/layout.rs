@@ -0,0 +1,237 @@
+#![cfg(feature = "layout")]
+
+mod common;
+
+use common::Shared;
+use compose_rt::layout::{Constraints, Layout, MeasureScope, Placement, Point, Size};
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, NodeKey, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Host;
+struct Item;
+struct Leaf;
+
+type Placed = Shared<Vec<NodeKey>>;
+
+// stacks the content's layout nodes, at least as wide as the widest one when `fill` is set
+struct Column<C> {
+    content: C,
+    fill: bool,
+    placed: Placed,
+}
+
+impl<C> Layout<TestNode> for Column<C>
+where
+    C: Fn(Scope<Item, TestNode>) + Clone + MaybeSendSync + 'static,
+{
+    fn measure(&self, scope: &mut MeasureScope<TestNode>, constraints: Constraints) -> Size {
+        let children = scope.subcompose::<Item, _>("content", self.content.clone());
+        let mut placeables = children
+            .iter()
+            .map(|child| child.measure(constraints.loosen()))
+            .collect::<Vec<_>>();
+        let width = placeables.iter().map(|p| p.width()).fold(0.0, f32::max);
+        if self.fill {
+            let fill = Constraints::new(width, width, 0.0, constraints.max_height);
+            placeables = children.iter().map(|child| child.measure(fill)).collect();
+        }
+        let mut placed = self.placed.borrow_mut();
+        placed.clear();
+        let mut y = 0.0;
+        for placeable in placeables {
+            scope.place(placeable, 0.0, y);
+            placed.push(placeable.node_key());
+            y += placeable.height();
+        }
+        Size::new(width, y)
+    }
+}
+
+fn leaf<S: 'static>(scope: Scope<S, TestNode>, size: Size) {
+    scope.layout(
+        scope.child::<Leaf>(),
+        move |_: &mut MeasureScope<TestNode>, _: Constraints| size,
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn column(
+    scope: Scope<Root, TestNode>,
+    items: State<Vec<Size>, TestNode>,
+    fill: bool,
+    placed: Placed,
+) {
+    let column = Column {
+        content: move |scope: Scope<Item, TestNode>| {
+            for size in items.get() {
+                leaf(scope, size);
+            }
+        },
+        fill,
+        placed,
+    };
+    scope.layout(
+        scope.child::<Host>(),
+        column,
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(fill: bool, placed: &Placed) -> Recomposer<Vec<Size>, TestNode> {
+    let placed = placed.clone();
+    Composer::compose_with(
+        move |scope, items| column(scope, items, fill, placed.clone()),
+        (),
+        || {
+            vec![
+                Size::new(10.0, 1.0),
+                Size::new(30.0, 2.0),
+                Size::new(20.0, 1.0),
+            ]
+        },
+    )
+}
+
+fn placements(recomposer: &Recomposer<Vec<Size>, TestNode>, placed: &Placed) -> Vec<Placement> {
+    placed
+        .borrow()
+        .iter()
+        .map(|node_key| recomposer.placement(*node_key).unwrap())
+        .collect()
+}
+
+fn host_children(recomposer: &Recomposer<Vec<Size>, TestNode>) -> Vec<NodeKey> {
+    recomposer.with_composer(|c| c.nodes[c.root_node_key()].children.clone())
+}
+
+#[test]
+fn column_places_children_below_each_other() {
+    let placed = Placed::default();
+    let mut recomposer = compose(false, &placed);
+    recomposer.layout(Constraints::loose(100.0, 100.0));
+
+    let root = recomposer.placement(recomposer.root_node_key()).unwrap();
+    assert_eq!(root.offset, Point::ZERO);
+    assert_eq!(root.size, Size::new(30.0, 4.0));
+    let offsets = placements(&recomposer, &placed)
+        .iter()
+        .map(|p| (p.offset.y, p.size.width))
+        .collect::<Vec<_>>();
+    assert_eq!(offsets, [(0.0, 10.0), (1.0, 30.0), (3.0, 20.0)]);
+}
+
+#[test]
+fn children_are_composed_when_measured() {
+    let placed = Placed::default();
+    let mut recomposer = compose(false, &placed);
+    assert!(host_children(&recomposer).is_empty());
+
+    let report = recomposer.layout(Constraints::loose(100.0, 100.0));
+    assert_eq!(host_children(&recomposer).len(), 1);
+    let nodes = recomposer.with_composer(|c| c.nodes.len());
+    // the slot and its three leaves
+    assert_eq!(report.mounted.len(), 4);
+
+    // the next pass reuses the slot
+    let report = recomposer.layout(Constraints::loose(100.0, 100.0));
+    assert_eq!(recomposer.with_composer(|c| c.nodes.len()), nodes);
+    assert!(report.mounted.is_empty());
+}
+
+#[test]
+fn children_are_remeasured_with_new_constraints() {
+    let placed = Placed::default();
+    let mut recomposer = compose(true, &placed);
+    recomposer.layout(Constraints::loose(100.0, 100.0));
+    let widths = placements(&recomposer, &placed)
+        .iter()
+        .map(|p| p.size.width)
+        .collect::<Vec<_>>();
+    assert_eq!(widths, [30.0, 30.0, 30.0]);
+
+    // the bounds win over what the node asks for
+    recomposer.layout(Constraints::loose(25.0, 3.0));
+    let root = recomposer.placement(recomposer.root_node_key()).unwrap();
+    assert_eq!(root.size, Size::new(25.0, 3.0));
+}
+
+#[test]
+fn recomposed_content_is_measured_in_the_next_pass() {
+    let placed = Placed::default();
+    let mut recomposer = compose(false, &placed);
+    recomposer.layout(Constraints::unbounded());
+    let first = placed.borrow().clone();
+
+    recomposer.recompose_with(vec![Size::new(10.0, 1.0), Size::new(50.0, 5.0)]);
+    recomposer.layout(Constraints::unbounded());
+    assert_eq!(placed.borrow()[..], first[..2]);
+    // dropped by the slot, so no longer placed
+    assert!(recomposer.placement(first[2]).is_none());
+    let root = recomposer.placement(recomposer.root_node_key()).unwrap();
+    assert_eq!(root.size, Size::new(50.0, 6.0));
+}
+
+#[test]
+fn slots_not_requested_are_unmounted() {
+    let created = Shared::new(0);
+    let c = created.clone();
+    let mut recomposer = Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let c = c.clone();
+            let layout = move |scope: &mut MeasureScope<TestNode>, constraints: Constraints| {
+                let mut height = 0.0;
+                let slots = if constraints.max_width >= 50.0 {
+                    vec!["title", "detail"]
+                } else {
+                    vec!["title"]
+                };
+                for slot in slots {
+                    let c = c.clone();
+                    let children = scope.subcompose::<Item, _>(slot, move |scope| {
+                        let c = c.clone();
+                        scope.use_state(move || c.set(c.get() + 1));
+                        leaf(scope, Size::new(10.0, 1.0));
+                    });
+                    for child in children {
+                        let placeable = child.measure(constraints);
+                        scope.place(placeable, 0.0, height);
+                        height += placeable.height();
+                    }
+                }
+                Size::new(constraints.max_width, height)
+            };
+            scope.layout(
+                scope.child::<Host>(),
+                layout,
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        (),
+    );
+    recomposer.layout(Constraints::loose(80.0, 10.0));
+    let children = recomposer.with_composer(|c| c.nodes[c.root_node_key()].children.clone());
+    assert_eq!(children.len(), 2);
+    assert_eq!(created.get(), 2);
+
+    let report = recomposer.layout(Constraints::loose(40.0, 10.0));
+    assert!(report.unmounted.contains(&children[1]));
+    recomposer.with_composer(|c| {
+        assert_eq!(c.nodes[c.root_node_key()].children, children[..1]);
+        assert!(!c.nodes.contains(children[1]));
+    });
+
+    recomposer.layout(Constraints::loose(80.0, 10.0));
+    assert_eq!(created.get(), 3);
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, CompositionLocal, MaybeSendSync, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(String);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+type Runs = Shared<Vec<&'static str>>;
+
+static THEME: CompositionLocal<&'static str> = CompositionLocal::new(|| "default");
+
+struct Container;
+struct Label;
+
+fn container<S, C>(scope: TestScope<S>, name: &'static str, runs: Runs, content: C)
+where
+    S: 'static,
+    C: Fn(TestScope<Container>) + Clone + MaybeSendSync + 'static,
+{
+    scope.create_node(
+        scope.child::<Container>(),
+        move |scope| {
+            runs.borrow_mut().push(name);
+            content(scope);
+        },
+        || (),
+        move |_, _| TestNode(name.to_string()),
+        |_, _, _| {},
+    );
+}
+
+fn themed_label<S: 'static>(scope: TestScope<S>, name: &'static str, runs: Runs) {
+    scope.create_node(
+        scope.child::<Label>(),
+        move |_| runs.borrow_mut().push(name),
+        move || format!("{} {}", name, scope.current(THEME)),
+        |label, _| TestNode(label),
+        |node, label, _| node.0 = label,
+    );
+}
+
+fn app(scope: TestScope<Root>, theme: State<&'static str, TestNode>, runs: Runs) {
+    let r = runs.clone();
+    container(scope, "provider", runs.clone(), move |scope| {
+        let runs = r.clone();
+        scope.provide(THEME, theme.get(), move |scope| {
+            let inner_runs = runs.clone();
+            container(scope, "plain", runs.clone(), move |scope| {
+                themed_label(scope, "deep", inner_runs.clone());
+            });
+            themed_label(scope, "direct", runs.clone());
+        });
+        themed_label(scope, "outside", r.clone());
+    });
+}
+
+fn labels(recomposer: &compose_rt::Recomposer<&'static str, TestNode>) -> Vec<String> {
+    recomposer.with_composer(|c| {
+        let mut labels = c
+            .nodes
+            .iter()
+            .filter_map(|(_, n)| n.data.as_ref().map(|d| d.0.clone()))
+            .collect::<Vec<_>>();
+        labels.sort();
+        labels
+    })
+}
+
+#[test]
+fn provided_value_invalidates_only_readers() {
+    let runs = Runs::default();
+    let app_runs = runs.clone();
+    let mut recomposer = Composer::compose_with(
+        move |s, theme| app(s, theme, app_runs.clone()),
+        (),
+        || "light",
+    );
+    assert_eq!(
+        labels(&recomposer),
+        [
+            "deep light",
+            "direct light",
+            "outside default",
+            "plain",
+            "provider"
+        ]
+    );
+
+    runs.borrow_mut().clear();
+    recomposer.recompose_with("dark");
+    assert_eq!(
+        labels(&recomposer),
+        [
+            "deep dark",
+            "direct dark",
+            "outside default",
+            "plain",
+            "provider"
+        ]
+    );
+    let mut reran = runs.borrow().clone();
+    reran.sort();
+    assert_eq!(reran, ["deep", "direct", "provider"]);
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(usize);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+
+struct Parent;
+struct Half;
+
+fn app(scope: TestScope<Root>, count: State<usize, TestNode>, runs: Shared<usize>) {
+    scope.create_node(
+        scope.child::<Parent>(),
+        move |scope| {
+            let half = count.get() / 2;
+            let runs = runs.clone();
+            scope.create_memo_node(
+                scope.child::<Half>(),
+                move |_| runs.set(runs.get() + 1),
+                move || half,
+                |half, _| TestNode(half),
+                |node, half, _| node.0 = half,
+            );
+        },
+        || (),
+        |_, _| TestNode(0),
+        |_, _, _| {},
+    );
+}
+
+fn half(recomposer: &Recomposer<usize, TestNode>) -> usize {
+    recomposer.with_composer(|c| {
+        let parent = &c.nodes[c.root_node_key()];
+        c.nodes[parent.children[0]].data.as_ref().unwrap().0
+    })
+}
+
+#[test]
+fn memo_node_skips_unchanged_args() {
+    let runs = Shared::new(0);
+    let r = runs.clone();
+    let mut recomposer = Composer::compose_with(move |s, c| app(s, c, r.clone()), (), || 2);
+    assert_eq!((half(&recomposer), runs.get()), (1, 1));
+
+    recomposer.recompose_with(3);
+    assert_eq!((half(&recomposer), runs.get()), (1, 1));
+
+    recomposer.recompose_with(4);
+    assert_eq!((half(&recomposer), runs.get()), (2, 2));
+
+    recomposer.recompose_with(5);
+    assert_eq!((half(&recomposer), runs.get()), (2, 2));
+}
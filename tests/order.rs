@@ -0,0 +1,172 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Item;
+
+type Log = Shared<Vec<usize>>;
+
+fn nested<S: 'static>(
+    scope: Scope<S, TestNode>,
+    depth: usize,
+    tick: State<i32, TestNode>,
+    log: Log,
+) {
+    scope.create_node(
+        scope.child::<Item>(),
+        move |scope| {
+            tick.get();
+            log.borrow_mut().push(depth);
+            if depth < 4 {
+                nested(scope, depth + 1, tick, log.clone());
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn parents_recompose_before_children_and_only_once() {
+    let log = Log::default();
+    let tick = Shared::new(None);
+    let (l, t) = (log.clone(), tick.clone());
+    let mut recomposer = Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let state = scope.use_state(|| 0);
+            t.set(Some(state));
+            nested(scope, 0, state, l.clone());
+        },
+        (),
+    );
+    log.borrow_mut().clear();
+
+    tick.get().unwrap().set(1);
+    let report = recomposer.recompose();
+    assert_eq!(*log.borrow(), [0, 1, 2, 3, 4]);
+    assert_eq!(report.recomposed.len(), 5);
+}
+
+#[test]
+fn children_removed_by_parent_are_not_recomposed() {
+    let stale_runs = Shared::new(0);
+    let show = Shared::new(None);
+    let (r, s) = (stale_runs.clone(), show.clone());
+    let mut recomposer = Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let (r, s) = (r.clone(), s.clone());
+            scope.create_node(
+                scope.child::<Item>(),
+                move |scope| {
+                    let visible = scope.use_state(|| true);
+                    s.set(Some(visible));
+                    if !visible.get() {
+                        return;
+                    }
+                    for i in 0..16 {
+                        let r = r.clone();
+                        scope.key(i, move |scope| {
+                            let r = r.clone();
+                            scope.create_node(
+                                scope.child::<Item>(),
+                                move |_| {
+                                    if !visible.get() {
+                                        r.set(r.get() + 1);
+                                    }
+                                },
+                                || (),
+                                |_, _| TestNode,
+                                |_, _, _| {},
+                            );
+                        });
+                    }
+                },
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        (),
+    );
+
+    show.get().unwrap().set(false);
+    let report = recomposer.recompose();
+    assert_eq!(stale_runs.get(), 0);
+    assert_eq!(report.recomposed.len(), 1);
+    assert_eq!(report.unmounted.len(), 16);
+}
+
+fn row(
+    scope: Scope<Root, TestNode>,
+    outer: State<i32, TestNode>,
+    tick: State<i32, TestNode>,
+    log: Log,
+) {
+    scope.create_node(
+        scope.child::<Item>(),
+        move |scope| {
+            outer.get();
+            log.borrow_mut().push(usize::MAX);
+            for i in 0..2000 {
+                let log = log.clone();
+                scope.key(i, move |scope| {
+                    let log = log.clone();
+                    scope.create_node(
+                        scope.child::<Item>(),
+                        move |_| {
+                            tick.get();
+                            log.borrow_mut().push(i);
+                        },
+                        || (),
+                        |_, _| TestNode,
+                        |_, _, _| {},
+                    );
+                });
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn large_dirty_sets_recompose_in_tree_order() {
+    let log = Log::default();
+    let states = Shared::new(None);
+    let (l, s) = (log.clone(), states.clone());
+    let mut recomposer = Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let outer = scope.use_state(|| 0);
+            let tick = scope.use_state(|| 0);
+            s.set(Some((outer, tick)));
+            row(scope, outer, tick, l.clone());
+        },
+        (),
+    );
+    let (outer, tick) = states.get().unwrap();
+    log.borrow_mut().clear();
+
+    tick.set(1);
+    let report = recomposer.recompose();
+    assert_eq!(report.recomposed.len(), 2000);
+    assert!(log.borrow().iter().copied().eq(0..2000));
+    log.borrow_mut().clear();
+
+    // the dirty row covers all of its dirty items
+    outer.set(1);
+    tick.set(2);
+    let report = recomposer.recompose();
+    assert_eq!(report.recomposed.len(), 2001);
+    assert_eq!(log.borrow()[0], usize::MAX);
+    assert!(log.borrow()[1..].iter().copied().eq(0..2000));
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{
+    ComposeNode, Composer, PrecomposeHandle, Recomposer, Root, Scope, SlotId, State,
+    SubcomposeScope,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Host;
+struct Page;
+struct Label;
+
+#[derive(Clone, Default, PartialEq)]
+struct Pages {
+    visible: Vec<u64>,
+    ahead: Option<u64>,
+}
+
+#[derive(Clone, Default)]
+struct Handles {
+    created: Shared<usize>,
+    label: Shared<Option<State<u64, TestNode>>>,
+    seen: Shared<Vec<(u64, u64)>>,
+    ahead: Shared<Option<PrecomposeHandle<TestNode>>>,
+}
+
+fn page(slot: SubcomposeScope<Page, TestNode, u64>, handles: Handles, label: State<u64, TestNode>) {
+    let created = handles.created.clone();
+    slot.use_state(move || created.set(created.get() + 1));
+    slot.create_node(
+        slot.child::<Label>(),
+        |_| {},
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+    handles
+        .seen
+        .borrow_mut()
+        .push((*slot.context(), label.get()));
+}
+
+fn app(scope: Scope<Root, TestNode>, pages: State<Pages, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Host>(),
+        move |scope| {
+            let label = scope.use_state(|| 0u64);
+            handles.label.set(Some(label));
+            let pages = pages.get();
+            let h = handles.clone();
+            let visible = pages.visible.clone();
+            let mut subcomposition = scope.subcompose(move |mut registry| {
+                for id in visible.clone() {
+                    let h = h.clone();
+                    registry.subcompose(SlotId::from(id), id, move |slot| {
+                        page(slot, h.clone(), label)
+                    });
+                }
+            });
+            if let Some(id) = pages.ahead {
+                let h = handles.clone();
+                let handle = subcomposition.precompose(SlotId::from(id), id, move |slot| {
+                    page(slot, h.clone(), label)
+                });
+                *handles.ahead.borrow_mut() = Some(handle);
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(handles: &Handles) -> Recomposer<Pages, TestNode> {
+    let h = handles.clone();
+    Composer::compose_with(
+        move |scope, pages| app(scope, pages, h.clone()),
+        (),
+        || Pages {
+            visible: vec![0],
+            ahead: Some(1),
+        },
+    )
+}
+
+fn host_children(recomposer: &Recomposer<Pages, TestNode>) -> Vec<usize> {
+    recomposer.with_composer(|c| c.nodes[c.root_node_key()].children.clone())
+}
+
+fn total(recomposer: &Recomposer<Pages, TestNode>) -> usize {
+    recomposer.with_composer(|c| c.nodes.len())
+}
+
+fn take_ahead(handles: &Handles) -> PrecomposeHandle<TestNode> {
+    handles.ahead.borrow_mut().take().unwrap()
+}
+
+#[test]
+fn precomposed_slot_is_adopted_when_requested() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    assert_eq!(host_children(&recomposer).len(), 1);
+    assert_eq!(handles.created.get(), 2);
+    let ahead = take_ahead(&handles);
+    assert!(!ahead.is_attached());
+    let nodes = total(&recomposer);
+
+    recomposer.recompose_with(Pages {
+        visible: vec![0, 1],
+        ahead: None,
+    });
+    assert_eq!(host_children(&recomposer)[1], ahead.node_key());
+    assert!(ahead.is_attached());
+    assert_eq!(handles.created.get(), 2);
+    assert_eq!(total(&recomposer), nodes);
+}
+
+#[test]
+fn precomposed_slot_survives_host_passes_and_catches_up() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let ahead = take_ahead(&handles);
+    let nodes = total(&recomposer);
+
+    recomposer.recompose_with(Pages {
+        visible: vec![0],
+        ahead: None,
+    });
+    assert_eq!(total(&recomposer), nodes);
+
+    handles.seen.borrow_mut().clear();
+    handles.label.get().unwrap().set(3);
+    recomposer.recompose();
+    assert_eq!(*handles.seen.borrow(), [(0, 3)]);
+
+    // attached outside a pass, it picks up the change on the next one
+    ahead.attach();
+    assert_eq!(host_children(&recomposer).len(), 2);
+    recomposer.recompose();
+    assert_eq!(*handles.seen.borrow(), [(0, 3), (1, 3)]);
+    assert_eq!(handles.created.get(), 2);
+}
+
+#[test]
+fn rekeyed_slot_is_reused_under_its_new_id() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let mut ahead = take_ahead(&handles);
+
+    assert!(ahead.has_key(&SlotId::from(1u64)));
+    ahead.rekey(SlotId::from(2u64));
+    assert!(ahead.has_key(&SlotId::from(2u64)));
+    assert!(!ahead.has_key(&SlotId::from(1u64)));
+    recomposer.recompose_with(Pages {
+        visible: vec![0, 2],
+        ahead: None,
+    });
+    assert_eq!(host_children(&recomposer)[1], ahead.node_key());
+    assert_eq!(handles.created.get(), 2);
+}
+
+#[test]
+fn disposed_slot_is_unmounted() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let nodes = total(&recomposer);
+
+    take_ahead(&handles).dispose();
+    assert_eq!(total(&recomposer), nodes - 2);
+
+    recomposer.recompose_with(Pages {
+        visible: vec![0, 1],
+        ahead: None,
+    });
+    assert_eq!(handles.created.get(), 3);
+    assert_eq!(total(&recomposer), nodes);
+}
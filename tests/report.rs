@@ -0,0 +1,117 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, NodeKey, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(&'static str);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Container;
+struct Label;
+struct Badge;
+
+type StateCell<T> = Shared<Option<State<T, TestNode>>>;
+
+#[derive(Clone, Default)]
+struct Handles {
+    count: StateCell<i32>,
+    show: StateCell<bool>,
+}
+
+fn leaf<S: 'static>(
+    scope: Scope<S, TestNode>,
+    name: &'static str,
+    value: impl Fn() -> i32 + Clone + MaybeSendSync + 'static,
+) {
+    scope.create_node(
+        scope.child::<Label>(),
+        |_| {},
+        value,
+        move |_, _| TestNode(name),
+        |_, _, _| {},
+    );
+}
+
+fn app(scope: Scope<Root, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Container>(),
+        move |scope| {
+            let count = scope.use_state(|| 0);
+            let show = scope.use_state(|| false);
+            handles.count.set(Some(count));
+            handles.show.set(Some(show));
+            leaf(scope, "counter", move || count.get());
+            leaf(scope, "static", || 0);
+            if show.get() {
+                scope.create_node(
+                    scope.child::<Badge>(),
+                    |scope| leaf(scope, "badge", || 1),
+                    || (),
+                    |_, _| TestNode("badge-box"),
+                    |_, _, _| {},
+                );
+            }
+        },
+        || (),
+        |_, _| TestNode("container"),
+        |_, _, _| {},
+    );
+}
+
+fn find(recomposer: &compose_rt::Recomposer<(), TestNode>, name: &str) -> Vec<NodeKey> {
+    recomposer.with_composer(|c| {
+        c.nodes
+            .iter()
+            .filter(|(_, n)| n.data.as_ref().is_some_and(|d| d.0 == name))
+            .map(|(key, _)| key)
+            .collect()
+    })
+}
+
+#[test]
+fn report_lists_what_a_pass_touched() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone()), ());
+    let container = find(&recomposer, "container")[0];
+    let counter = find(&recomposer, "counter")[0];
+    let fixed = find(&recomposer, "static")[0];
+    let count = handles.count.get().unwrap();
+    let show = handles.show.get().unwrap();
+
+    let report = recomposer.recompose();
+    assert!(report.is_empty());
+
+    count.set(1);
+    show.set(true);
+    let report = recomposer.recompose();
+    assert_eq!(report.dirty_states.len(), 2);
+    assert!(report.dirty_states.contains(&count.id));
+    assert_eq!(
+        report
+            .recomposed
+            .iter()
+            .filter(|k| **k == container)
+            .count(),
+        1
+    );
+    assert!(report.recomposed.contains(&counter));
+    assert!(report.updated.contains(&counter));
+    assert!(report.skipped.contains(&fixed));
+    let badge = [
+        find(&recomposer, "badge-box")[0],
+        find(&recomposer, "badge")[0],
+    ];
+    assert_eq!(report.mounted, badge);
+    assert!(report.unmounted.is_empty());
+
+    show.set(false);
+    let report = recomposer.recompose();
+    assert!(report.mounted.is_empty());
+    assert_eq!(report.unmounted, [badge[1], badge[0]]);
+    assert_eq!(report.updated, [container]);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Recomposer, Root, Scope, SlotId, SlotRetention, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Host;
+struct SlotItem;
+
+#[derive(Clone, Default)]
+struct Handles {
+    created: Shared<usize>,
+    label: Shared<Option<State<u64, TestNode>>>,
+    seen: Shared<u64>,
+}
+
+fn app(
+    scope: Scope<Root, TestNode>,
+    slots: State<Vec<u64>, TestNode>,
+    retention: SlotRetention,
+    handles: Handles,
+) {
+    scope.create_node(
+        scope.child::<Host>(),
+        move |scope| {
+            let label = scope.use_state(|| 0u64);
+            handles.label.set(Some(label));
+            let requested = slots.get();
+            let handles = handles.clone();
+            let mut subcomposition = scope.subcompose(move |mut registry| {
+                for id in requested.clone() {
+                    let handles = handles.clone();
+                    registry.subcompose::<SlotItem, _, _>(SlotId::from(id), id, move |slot| {
+                        let created = handles.created.clone();
+                        slot.use_state(move || created.set(created.get() + 1));
+                        handles.seen.set(label.get());
+                    });
+                }
+            });
+            subcomposition.set_retention(retention);
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(retention: SlotRetention, handles: &Handles) -> Recomposer<Vec<u64>, TestNode> {
+    let h = handles.clone();
+    Composer::compose_with(
+        move |scope, slots| app(scope, slots, retention, h.clone()),
+        (),
+        || vec![1, 2, 3],
+    )
+}
+
+fn attached(recomposer: &Recomposer<Vec<u64>, TestNode>) -> usize {
+    recomposer.with_composer(|c| c.nodes[c.root_node_key()].children.len())
+}
+
+fn total(recomposer: &Recomposer<Vec<u64>, TestNode>) -> usize {
+    recomposer.with_composer(|c| c.nodes.len())
+}
+
+#[test]
+fn discard_drops_slots_immediately() {
+    let handles = Handles::default();
+    let mut recomposer = compose(SlotRetention::Discard, &handles);
+    let nodes = total(&recomposer);
+
+    recomposer.recompose_with(vec![1]);
+    assert_eq!(attached(&recomposer), 1);
+    assert_eq!(total(&recomposer), nodes - 2);
+
+    recomposer.recompose_with(vec![1, 2, 3]);
+    assert_eq!(handles.created.get(), 5);
+}
+
+#[test]
+fn keep_recent_retains_the_latest_slots() {
+    let handles = Handles::default();
+    let mut recomposer = compose(SlotRetention::KeepRecent(1), &handles);
+    let nodes = total(&recomposer);
+
+    let report = recomposer.recompose_with(vec![]);
+    assert_eq!(attached(&recomposer), 0);
+    assert_eq!(total(&recomposer), nodes - 2);
+    assert_eq!(report.unmounted.len(), 2);
+
+    // slot 3 was requested last, so it comes back with its state
+    recomposer.recompose_with(vec![3]);
+    assert_eq!(attached(&recomposer), 1);
+    assert_eq!(handles.created.get(), 3);
+
+    recomposer.recompose_with(vec![1, 2, 3]);
+    assert_eq!(handles.created.get(), 5);
+    assert_eq!(total(&recomposer), nodes);
+}
+
+#[test]
+fn keep_passes_evicts_after_missed_passes() {
+    let handles = Handles::default();
+    let mut recomposer = compose(SlotRetention::KeepPasses(1), &handles);
+
+    recomposer.recompose_with(vec![1]);
+    recomposer.recompose_with(vec![1, 2]);
+    assert_eq!(handles.created.get(), 3);
+
+    // slot 3 missed two passes by now
+    recomposer.recompose_with(vec![1]);
+    recomposer.recompose_with(vec![1, 3]);
+    assert_eq!(handles.created.get(), 4);
+    recomposer.recompose_with(vec![1, 2]);
+    assert_eq!(handles.created.get(), 5);
+}
+
+#[test]
+fn retained_slot_catches_up_on_reattach() {
+    let handles = Handles::default();
+    let mut recomposer = compose(SlotRetention::KeepRecent(3), &handles);
+
+    recomposer.recompose_with(vec![]);
+    handles.label.get().unwrap().set(7);
+    recomposer.recompose();
+    assert_eq!(handles.seen.get(), 0);
+
+    recomposer.recompose_with(vec![3]);
+    assert_eq!(handles.seen.get(), 7);
+    assert_eq!(handles.created.get(), 3);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Root, Scope, State, StateError, StructuralEquality};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type Handle = Shared<Option<State<i32, TestNode>>>;
+
+struct Reader;
+
+fn app(scope: Scope<Root, TestNode>, handle: Handle, runs: Shared<usize>) {
+    scope.create_node(
+        scope.child::<Reader>(),
+        move |scope| {
+            let count = scope.use_state_with_policy(|| 0, StructuralEquality);
+            handle.set(Some(count));
+            count.get();
+            runs.set(runs.get() + 1);
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn updates_from_background_thread_apply_on_recompose() {
+    let handle = Handle::default();
+    let runs = Shared::new(0);
+    let (h, r) = (handle.clone(), runs.clone());
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone(), r.clone()), ());
+    let count = handle.get().unwrap();
+    let wakes = Arc::new(AtomicUsize::new(0));
+    let w = wakes.clone();
+    recomposer.set_waker(move || {
+        w.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let sender = count.sender();
+    thread::spawn(move || {
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+    })
+    .join()
+    .unwrap();
+
+    // only the transition to a non-empty inbox wakes the host
+    assert_eq!(wakes.load(Ordering::SeqCst), 1);
+    assert_eq!(count.get_untracked(), 0);
+    recomposer.recompose();
+    assert_eq!(count.get_untracked(), 2);
+    assert_eq!(runs.get(), 2);
+
+    // unchanged values are dropped by the state's mutation policy
+    count.sender().send(2).unwrap();
+    assert_eq!(wakes.load(Ordering::SeqCst), 2);
+    recomposer.recompose();
+    assert_eq!(runs.get(), 2);
+}
+
+#[test]
+fn send_after_composer_dropped() {
+    let handle = Handle::default();
+    let h = handle.clone();
+    let recomposer = Composer::compose(move |s| app(s, h.clone(), Shared::default()), ());
+    let sender = handle.get().unwrap().sender();
+    drop(recomposer);
+    assert_eq!(
+        sender.send(1),
+        Err(StateError::ComposerDropped(sender.id()))
+    );
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use std::hash::{Hash, Hasher};
+
+use common::Shared;
+use compose_rt::{
+    ComposeNode, Composer, MaybeSendSync, Root, Scope, SlotId, State, SubcomposeHandle,
+    SubcomposeScope,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Host;
+struct SlotItem;
+
+// every key lands in the same hash bucket
+#[derive(Clone, PartialEq, Eq)]
+struct Colliding(u32);
+
+impl Hash for Colliding {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        0u8.hash(state);
+    }
+}
+
+#[derive(Clone, Default)]
+struct Handles {
+    created: Shared<usize>,
+    seen: Shared<Vec<u32>>,
+    slots: Shared<Vec<SubcomposeHandle>>,
+}
+
+fn host<K>(scope: Scope<Root, TestNode>, keys: State<Vec<(K, u32)>, TestNode>, handles: Handles)
+where
+    K: Hash + Eq + Clone + MaybeSendSync + 'static,
+{
+    scope.create_node(
+        scope.child::<Host>(),
+        move |scope| {
+            let keys = keys.get();
+            let handles = handles.clone();
+            handles.slots.borrow_mut().clear();
+            scope.subcompose(move |mut registry| {
+                for (key, value) in keys.clone() {
+                    let h = handles.clone();
+                    let handle = registry.subcompose::<SlotItem, _, _>(key, (), move |slot| {
+                        let created = h.created.clone();
+                        let state = slot.use_state(move || {
+                            created.set(created.get() + 1);
+                            value
+                        });
+                        h.seen.borrow_mut().push(state.get());
+                    });
+                    handles.slots.borrow_mut().push(handle);
+                }
+            });
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn node_keys(handles: &Handles) -> Vec<usize> {
+    handles
+        .slots
+        .borrow()
+        .iter()
+        .map(|h| h.node_key())
+        .collect()
+}
+
+#[test]
+fn owned_string_keys_reuse_slots() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose_with(
+        move |scope, keys| host(scope, keys, h.clone()),
+        (),
+        || vec![("header".to_string(), 1), ("body".to_string(), 2)],
+    );
+    let initial = node_keys(&handles);
+
+    // the keys are compared by value, not by where the strings live
+    recomposer.recompose_with(vec![
+        (String::from("header"), 10),
+        (String::from("body"), 20),
+        (String::from("footer"), 3),
+    ]);
+    assert_eq!(node_keys(&handles)[..2], initial[..]);
+    assert_eq!(handles.created.get(), 3);
+}
+
+#[test]
+fn colliding_keys_get_their_own_slots() {
+    let handles = Handles::default();
+    let h = handles.clone();
+    let mut recomposer = Composer::compose_with(
+        move |scope, keys| host(scope, keys, h.clone()),
+        (),
+        || (0..4).map(|i| (Colliding(i), i)).collect(),
+    );
+    assert_eq!(handles.created.get(), 4);
+    assert_eq!(*handles.seen.borrow(), [0, 1, 2, 3]);
+    let mut initial = node_keys(&handles);
+
+    // reordering moves each slot's own node
+    recomposer.recompose_with((0..4).rev().map(|i| (Colliding(i), 0)).collect());
+    initial.reverse();
+    assert_eq!(node_keys(&handles), initial);
+    assert_eq!(handles.created.get(), 4);
+}
+
+#[test]
+fn keys_of_different_types_do_not_match() {
+    let created = Shared::new(0);
+    let c = created.clone();
+    Composer::compose(
+        move |scope: Scope<Root, TestNode>| {
+            let c = c.clone();
+            scope.create_node(
+                scope.child::<Host>(),
+                move |scope| {
+                    let c = c.clone();
+                    scope.subcompose(move |mut registry| {
+                        let c = c.clone();
+                        let slot = move |slot: SubcomposeScope<SlotItem, _, _>| {
+                            let c = c.clone();
+                            slot.use_state(move || c.set(c.get() + 1));
+                        };
+                        registry.subcompose(1u32, (), slot.clone());
+                        registry.subcompose(1u64, (), slot.clone());
+                        registry.subcompose("1", (), slot.clone());
+                        registry.subcompose(String::from("1"), (), slot);
+                    });
+                },
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        (),
+    );
+    assert_eq!(created.get(), 4);
+}
+
+#[test]
+fn slot_ids_match_their_raw_value() {
+    let created = Shared::new(0);
+    let slots = Shared::new(Vec::new());
+    let (c, s) = (created.clone(), slots.clone());
+    let mut recomposer = Composer::compose_with(
+        move |scope: Scope<Root, TestNode>, raw: State<bool, TestNode>| {
+            let (c, s) = (c.clone(), s.clone());
+            scope.create_node(
+                scope.child::<Host>(),
+                move |scope| {
+                    let raw = raw.get();
+                    let (c, s) = (c.clone(), s.clone());
+                    scope.subcompose(move |mut registry| {
+                        let c = c.clone();
+                        let slot = move |slot: SubcomposeScope<SlotItem, _, _>| {
+                            let c = c.clone();
+                            slot.use_state(move || c.set(c.get() + 1));
+                        };
+                        let handle = if raw {
+                            registry.subcompose(7u64, (), slot)
+                        } else {
+                            registry.subcompose(SlotId::from(7u64), (), slot)
+                        };
+                        s.borrow_mut().push(handle.node_key());
+                    });
+                },
+                || (),
+                |_, _| TestNode,
+                |_, _, _| {},
+            );
+        },
+        (),
+        || false,
+    );
+
+    recomposer.recompose_with(true);
+    recomposer.recompose_with(false);
+    let slots = slots.get();
+    assert_eq!(slots.len(), 3);
+    assert!(slots.iter().all(|key| *key == slots[0]));
+    assert_eq!(created.get(), 1);
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{
+    ComposeNode, Composer, Recomposer, Root, Scope, SlotId, SlotRetention, SlotStatus, State,
+    SubcomposeHandle,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Host;
+struct SlotItem;
+struct Inner;
+
+type StateCell = Shared<Option<State<u64, TestNode>>>;
+
+#[derive(Clone, Default)]
+struct Handles {
+    tick: StateCell,
+    label: StateCell,
+    inner: StateCell,
+    slots: Shared<Vec<SubcomposeHandle>>,
+}
+
+fn app(scope: Scope<Root, TestNode>, requested: State<Vec<u64>, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Host>(),
+        move |scope| {
+            let tick = scope.use_state(|| 0);
+            let label = scope.use_state(|| 0);
+            let inner = scope.use_state(|| 0);
+            handles.tick.set(Some(tick));
+            handles.label.set(Some(label));
+            handles.inner.set(Some(inner));
+            tick.get();
+            let requested = requested.get();
+            let slots = handles.slots.clone();
+            slots.borrow_mut().clear();
+            let mut subcomposition = scope.subcompose(move |mut registry| {
+                for id in requested.clone() {
+                    let handle =
+                        registry.subcompose::<SlotItem, _, _>(SlotId::from(id), id, move |slot| {
+                            label.get();
+                            slot.create_node(
+                                slot.child::<Inner>(),
+                                move |_| {
+                                    inner.get();
+                                },
+                                || (),
+                                |_, _| TestNode,
+                                |_, _, _| {},
+                            );
+                        });
+                    slots.borrow_mut().push(handle);
+                }
+            });
+            subcomposition.set_retention(SlotRetention::KeepRecent(4));
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(handles: &Handles) -> Recomposer<Vec<u64>, TestNode> {
+    let h = handles.clone();
+    Composer::compose_with(
+        move |scope, requested| app(scope, requested, h.clone()),
+        (),
+        || vec![0, 1],
+    )
+}
+
+fn bump(state: &StateCell) {
+    let state = state.get().unwrap();
+    state.set(state.get_untracked() + 1);
+}
+
+fn statuses(handles: &Handles) -> Vec<(SlotStatus, bool)> {
+    handles
+        .slots
+        .borrow()
+        .iter()
+        .map(|h| (h.status(), h.is_reused()))
+        .collect()
+}
+
+fn generations(handles: &Handles) -> Vec<u64> {
+    handles
+        .slots
+        .borrow()
+        .iter()
+        .map(|h| h.generation())
+        .collect()
+}
+
+#[test]
+fn status_follows_what_the_call_did() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    assert_eq!(
+        statuses(&handles),
+        [(SlotStatus::Created, false), (SlotStatus::Created, false)]
+    );
+    let initial = generations(&handles);
+
+    bump(&handles.tick);
+    recomposer.recompose();
+    assert_eq!(
+        statuses(&handles),
+        [(SlotStatus::Skipped, false), (SlotStatus::Skipped, false)]
+    );
+    assert_eq!(generations(&handles), initial);
+
+    bump(&handles.tick);
+    bump(&handles.label);
+    recomposer.recompose();
+    assert_eq!(
+        statuses(&handles),
+        [
+            (SlotStatus::Recomposed, false),
+            (SlotStatus::Recomposed, false)
+        ]
+    );
+    assert!(generations(&handles)
+        .iter()
+        .zip(&initial)
+        .all(|(now, before)| now > before));
+}
+
+#[test]
+fn generation_changes_when_nested_content_recomposes() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let initial = generations(&handles);
+
+    // only the inner nodes recompose, the host sees the new generations next time
+    bump(&handles.inner);
+    recomposer.recompose();
+    bump(&handles.tick);
+    recomposer.recompose();
+    assert_eq!(
+        statuses(&handles),
+        [(SlotStatus::Skipped, false), (SlotStatus::Skipped, false)]
+    );
+    let nested = generations(&handles);
+    assert!(nested
+        .iter()
+        .zip(&initial)
+        .all(|(now, before)| now > before));
+
+    bump(&handles.tick);
+    recomposer.recompose();
+    assert_eq!(generations(&handles), nested);
+}
+
+#[test]
+fn retained_slot_is_reported_as_reused() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    let initial = generations(&handles);
+
+    recomposer.recompose_with(vec![0]);
+    recomposer.recompose_with(vec![0, 1]);
+    assert_eq!(
+        statuses(&handles),
+        [(SlotStatus::Skipped, false), (SlotStatus::Skipped, true)]
+    );
+    assert_eq!(generations(&handles), initial);
+}
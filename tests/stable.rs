@@ -0,0 +1,59 @@
+mod common;
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, RecomposeError, Root, Scope};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Counter;
+
+// bumps its own state during composition until it reaches `limit`
+fn app(scope: Scope<Root, TestNode>, limit: usize, runs: Shared<usize>) {
+    scope.create_node(
+        scope.child::<Counter>(),
+        move |scope| {
+            runs.set(runs.get() + 1);
+            let count = scope.use_state(|| 0);
+            let value = count.get();
+            if value < limit {
+                count.set(value + 1);
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn repeats_passes_until_no_state_is_dirty() {
+    let runs = Shared::new(0);
+    let r = runs.clone();
+    let mut recomposer = Composer::compose(move |s| app(s, 3, r.clone()), ());
+    assert_eq!(recomposer.recompose_until_stable(10), Ok(3));
+    assert_eq!(runs.get(), 4);
+    assert_eq!(recomposer.recompose_until_stable(10), Ok(1));
+    assert_eq!(runs.get(), 4);
+}
+
+#[test]
+fn reports_states_that_never_settle() {
+    let mut recomposer = Composer::compose(move |s| app(s, usize::MAX, Shared::default()), ());
+    let err = recomposer.recompose_until_stable(5).unwrap_err();
+    let RecomposeError::Unstable { iterations, states } = &err;
+    assert_eq!(*iterations, 5);
+    assert_eq!(states.len(), 1);
+    assert!(err.to_string().contains("tests/stable.rs:21"), "{}", err);
+}
+
+#[test]
+#[should_panic(expected = "recompose_until_stable needs at least one pass")]
+fn zero_iterations_are_rejected() {
+    let mut recomposer = Composer::compose(move |s| app(s, 0, Shared::default()), ());
+    let _ = recomposer.recompose_until_stable(0);
+}
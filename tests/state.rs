@@ -0,0 +1,144 @@
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use common::Shared;
+use compose_rt::{
+    ComposeNode, Composer, NeverEqual, Root, Scope, State, StateError, StructuralEquality,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+type Handles = Shared<Option<[State<i32, TestNode>; 3]>>;
+
+struct Reader;
+
+fn reader<S: 'static>(scope: TestScope<S>, state: State<i32, TestNode>, runs: Shared<usize>) {
+    scope.create_node(
+        scope.child::<Reader>(),
+        move |_| {
+            state.get();
+            runs.set(runs.get() + 1);
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn app(scope: TestScope<Root>, handles: Handles, runs: [Shared<usize>; 3]) {
+    scope.create_node(
+        scope.child::<Reader>(),
+        move |scope| {
+            let structural = scope.use_state_with_policy(|| 1, StructuralEquality);
+            let never = scope.use_state_with_policy(|| 1, NeverEqual);
+            let same_sign =
+                scope.use_state_with_policy(|| 1, |a: &i32, b: &i32| a.signum() == b.signum());
+            handles.set(Some([structural, never, same_sign]));
+            reader(scope, structural, runs[0].clone());
+            reader(scope, never, runs[1].clone());
+            reader(scope, same_sign, runs[2].clone());
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn set_respects_mutation_policy() {
+    let handles = Handles::default();
+    let runs: [Shared<usize>; 3] = Default::default();
+    let (h, r) = (handles.clone(), runs.clone());
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone(), r.clone()), ());
+    let [structural, never, same_sign] = handles.get().unwrap();
+    let counts = || runs.iter().map(|r| r.get()).collect::<Vec<_>>();
+    assert_eq!(counts(), [1, 1, 1]);
+
+    structural.set(1);
+    never.set(1);
+    same_sign.set(5);
+    recomposer.recompose();
+    assert_eq!(counts(), [1, 2, 1]);
+    assert_eq!(same_sign.get_untracked(), 1);
+
+    structural.set(2);
+    same_sign.set(-5);
+    recomposer.recompose();
+    assert_eq!(counts(), [2, 2, 2]);
+    assert_eq!(same_sign.get_untracked(), -5);
+}
+
+#[test]
+fn with_mut_ignores_mutation_policy() {
+    let handles = Handles::default();
+    let runs: [Shared<usize>; 3] = Default::default();
+    let (h, r) = (handles.clone(), runs.clone());
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone(), r.clone()), ());
+    let [structural, _, same_sign] = handles.get().unwrap();
+    let counts = || runs.iter().map(|r| r.get()).collect::<Vec<_>>();
+
+    // the value is changed in place, so there is nothing to compare it with
+    structural.with_mut(|v| *v = 1);
+    same_sign.with_mut(|v| *v = 5);
+    recomposer.recompose();
+    assert_eq!(counts(), [2, 1, 2]);
+    assert_eq!(same_sign.get_untracked(), 5);
+}
+
+fn conditional(
+    scope: TestScope<Root>,
+    show: State<bool, TestNode>,
+    handle: Shared<Option<State<i32, TestNode>>>,
+) {
+    scope.create_node(
+        scope.child::<Reader>(),
+        move |scope| {
+            if show.get() {
+                let handle = handle.clone();
+                scope.create_node(
+                    scope.child::<Reader>(),
+                    move |scope| handle.set(Some(scope.use_state(|| 7))),
+                    || (),
+                    |_, _| TestNode,
+                    |_, _, _| {},
+                );
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn fallible_accessors_report_disposed_state() {
+    let handle = Shared::new(None);
+    let h = handle.clone();
+    let mut recomposer =
+        Composer::compose_with(move |s, show| conditional(s, show, h.clone()), (), || true);
+    let state = handle.get().unwrap();
+    assert_eq!(state.try_get(), Ok(7));
+    assert_eq!(state.try_set(8), Ok(()));
+    assert_eq!(state.try_with(|v| v * 2), Ok(16));
+
+    recomposer.recompose_with(false);
+    assert_eq!(state.try_get(), Err(StateError::NodeDisposed(state.id)));
+    let panic = panic::catch_unwind(AssertUnwindSafe(|| state.get())).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("tests/state.rs"), "{}", message);
+    assert!(
+        message.contains(&state.id.node_key().to_string()),
+        "{}",
+        message
+    );
+
+    drop(recomposer);
+    assert_eq!(state.try_set(1), Err(StateError::ComposerDropped(state.id)));
+}
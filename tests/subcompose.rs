@@ -63,7 +63,7 @@ fn slot_keys(recomposer: &mut compose_rt::Recomposer<usize, TestNode>) -> Vec<co
 
 #[test]
 fn subcompose_reuses_and_replaces_slots() {
-    let mut recomposer = Composer::compose_with(app, TestContext::default(), || 2usize);
+    let mut recomposer = Composer::compose_with(app, TestContext, || 2usize);
 
     let initial = slot_keys(&mut recomposer);
     assert_eq!(initial.len(), 2);
@@ -0,0 +1,78 @@
+#![cfg(feature = "sync")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use compose_rt::{ComposeNode, Composer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(i32);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+struct Counter;
+
+fn app(
+    scope: Scope<Root, TestNode>,
+    handle: Arc<Mutex<Option<State<i32, TestNode>>>>,
+    runs: Arc<AtomicUsize>,
+) {
+    scope.create_node(
+        scope.child::<Counter>(),
+        move |scope| {
+            let count = scope.use_state(|| 0);
+            *handle.lock().unwrap() = Some(count);
+            let value = count.get();
+            runs.fetch_add(1, Ordering::SeqCst);
+            scope.create_node(
+                scope.child::<Counter>(),
+                |_| {},
+                move || value,
+                |value, _| TestNode(value),
+                |node, value, _| node.0 = value,
+            );
+        },
+        || (),
+        |_, _| TestNode(-1),
+        |_, _, _| {},
+    );
+}
+
+#[test]
+fn state_set_from_worker_thread() {
+    let handle = Arc::new(Mutex::new(None));
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (h, r) = (handle.clone(), runs.clone());
+    let mut recomposer = Composer::compose(move |s| app(s, h.clone(), r.clone()), ());
+    let count = handle.lock().unwrap().unwrap();
+
+    thread::spawn(move || count.set(42)).join().unwrap();
+    recomposer.recompose();
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(count.get_untracked(), 42);
+}
+
+#[test]
+fn recomposer_runs_on_ui_thread() {
+    let handle = Arc::new(Mutex::new(None));
+    let runs = Arc::new(AtomicUsize::new(0));
+    let (h, r) = (handle.clone(), runs.clone());
+    let recomposer = Composer::compose(move |s| app(s, h.clone(), r.clone()), ());
+    let count = handle.lock().unwrap().unwrap();
+
+    count.set(7);
+    let ui = thread::spawn(move || {
+        let mut recomposer = recomposer;
+        recomposer.recompose();
+        recomposer
+    });
+    let recomposer = ui.join().unwrap();
+
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+    assert_eq!(count.get_untracked(), 7);
+    drop(recomposer);
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use common::Shared;
+use compose_rt::{ComposeNode, Composer, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode;
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type Slot = Shared<Option<i32>>;
+
+// resolves once the test puts a value into the slot
+struct Recv(Slot);
+
+impl Future for Recv {
+    type Output = i32;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<i32> {
+        match self.0.take() {
+            Some(value) => Poll::Ready(value),
+            None => Poll::Pending,
+        }
+    }
+}
+
+struct DropCounter(Shared<usize>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+struct Fetcher;
+
+type StateCell = Shared<Option<State<i32, TestNode>>>;
+
+#[derive(Clone, Default)]
+struct Handles {
+    slot: Slot,
+    drops: Shared<usize>,
+    show: Shared<Option<State<bool, TestNode>>>,
+    query: StateCell,
+    result: StateCell,
+}
+
+fn fetcher<S: 'static>(scope: Scope<S, TestNode>, query: State<i32, TestNode>, handles: Handles) {
+    scope.create_node(
+        scope.child::<Fetcher>(),
+        move |scope| {
+            let result = scope.use_state(|| 0);
+            handles.result.set(Some(result));
+            let q = query.get();
+            let (slot, drops) = (handles.slot.clone(), handles.drops.clone());
+            scope.use_task(q, move || async move {
+                let _guard = DropCounter(drops);
+                let value = Recv(slot).await;
+                result.set(q * 100 + value);
+            });
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn app(scope: Scope<Root, TestNode>, handles: Handles) {
+    let show = scope.use_state(|| true);
+    let query = scope.use_state(|| 1);
+    handles.show.set(Some(show));
+    handles.query.set(Some(query));
+    scope.create_node(
+        scope.child::<Fetcher>(),
+        move |scope| {
+            if show.get() {
+                fetcher(scope, query, handles.clone());
+            }
+        },
+        || (),
+        |_, _| TestNode,
+        |_, _, _| {},
+    );
+}
+
+fn compose(handles: &Handles) -> Recomposer<(), TestNode> {
+    let h = handles.clone();
+    Composer::compose(move |scope| app(scope, h.clone()), ())
+}
+
+fn poll(recomposer: &mut Recomposer<(), TestNode>) -> Poll<()> {
+    recomposer.poll_tasks(&mut Context::from_waker(Waker::noop()))
+}
+
+#[test]
+fn task_completes_and_sets_state() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+
+    assert_eq!(poll(&mut recomposer), Poll::Pending);
+    handles.slot.set(Some(7));
+    assert_eq!(poll(&mut recomposer), Poll::Ready(()));
+    assert_eq!(handles.drops.get(), 1);
+    assert_eq!(handles.result.get().unwrap().get_untracked(), 107);
+
+    // a finished task is not relaunched while its keys are unchanged
+    recomposer.recompose();
+    assert_eq!(poll(&mut recomposer), Poll::Ready(()));
+}
+
+#[test]
+fn task_restarts_when_keys_change() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    assert_eq!(poll(&mut recomposer), Poll::Pending);
+
+    handles.query.get().unwrap().set(2);
+    recomposer.recompose();
+    assert_eq!(handles.drops.get(), 1);
+    assert_eq!(poll(&mut recomposer), Poll::Pending);
+    handles.slot.set(Some(5));
+    assert_eq!(poll(&mut recomposer), Poll::Ready(()));
+    assert_eq!(handles.drops.get(), 2);
+    assert_eq!(handles.result.get().unwrap().get_untracked(), 205);
+}
+
+#[test]
+fn task_dropped_on_unmount() {
+    let handles = Handles::default();
+    let mut recomposer = compose(&handles);
+    assert_eq!(poll(&mut recomposer), Poll::Pending);
+
+    handles.show.get().unwrap().set(false);
+    recomposer.recompose();
+    assert_eq!(handles.drops.get(), 1);
+    assert_eq!(poll(&mut recomposer), Poll::Ready(()));
+}
@@ -0,0 +1,78 @@
+use compose_rt::{ComposeNode, Composer, MaybeSendSync, Recomposer, Root, Scope, State};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestNode(&'static str);
+
+impl ComposeNode for TestNode {
+    type Context = ();
+}
+
+type TestScope<S> = Scope<S, TestNode>;
+
+struct Container;
+struct Leaf;
+
+fn container<S, C>(scope: TestScope<S>, content: C)
+where
+    S: 'static,
+    C: Fn(TestScope<Container>) + Clone + MaybeSendSync + 'static,
+{
+    scope.create_node(
+        scope.child::<Container>(),
+        content,
+        || (),
+        |_, _| TestNode("container"),
+        |_, _, _| {},
+    );
+}
+
+fn leaf<S>(scope: TestScope<S>, counter: State<usize, TestNode>)
+where
+    S: 'static,
+{
+    scope.create_node(
+        scope.child::<Leaf>(),
+        move |scope| {
+            let local = scope.use_state(|| 0usize);
+            local.set(local.get_untracked() + 1);
+            counter.set(local.get_untracked());
+        },
+        || (),
+        |_, _| TestNode("leaf"),
+        |_, _, _| {},
+    );
+}
+
+fn app(scope: TestScope<Root>, show: State<bool, TestNode>) {
+    container(scope, move |scope| {
+        let counter = scope.use_state(|| 0usize);
+        if show.get() {
+            container(scope, move |scope| {
+                container(scope, move |scope| {
+                    leaf(scope, counter);
+                    leaf(scope, counter);
+                });
+            });
+        }
+    });
+}
+
+fn node_count(recomposer: &Recomposer<bool, TestNode>) -> usize {
+    recomposer.with_composer(|composer| composer.nodes.len())
+}
+
+#[test]
+fn unmount_disposes_whole_subtree() {
+    let mut recomposer = Composer::compose_with(app, (), || false);
+    let hidden = node_count(&recomposer);
+
+    recomposer.recompose_with(true);
+    assert_eq!(node_count(&recomposer), hidden + 4);
+
+    for _ in 0..3 {
+        recomposer.recompose_with(false);
+        assert_eq!(node_count(&recomposer), hidden);
+        recomposer.recompose_with(true);
+        assert_eq!(node_count(&recomposer), hidden + 4);
+    }
+}